@@ -30,6 +30,18 @@ struct Ers_Api {
     Ers_String_View (*get_curr_row)(void *editor);
     void (*update_curr_row)(void *editor, Ers_String_View row);
     void (*on_render)(void *plugin, void (*callback)(Ers_Api *api, void *user_data), void *user_data);
+    // Shows the first character of `sign` next to `row` (zero-based). An empty sign removes it.
+    void (*set_sign)(void *plugin, size_t row, Ers_String_View sign);
+    void (*clear_signs)(void *plugin);
+    // Registers a status line segment called `name`. The callback returns the text to show in it,
+    // or an empty string to hide it.
+    void (*add_segment)
+        (
+            void *plugin,
+            Ers_String_View name,
+            Ers_String_View (*callback)(Ers_Api *api, void *user_data),
+            void *user_data
+        );
 };
 
 
@@ -0,0 +1,133 @@
+use crate::langs::{Language, LexedRows, Scope, Word};
+
+const PAIRS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+/// How far to look for a matching bracket or the nesting depth, so that huge files stay responsive
+pub const MAX_SCAN_ROWS: usize = 1000;
+
+/// The opening and closing bracket of the pair `ch` belongs to
+fn pair(ch: char) -> Option<(char, char)> {
+    PAIRS
+        .iter()
+        .copied()
+        .find(|&(open, close)| ch == open || ch == close)
+}
+
+pub fn is_open(ch: char) -> bool {
+    PAIRS.iter().any(|&(open, _)| ch == open)
+}
+
+/// Brackets on `line` along with their columns, leaving out the ones its `words` put in strings
+/// or comments
+pub fn brackets(line: &[char], words: &[Word]) -> Vec<(usize, char)> {
+    let mut is_code = vec![true; line.len()];
+    for word in words {
+        if matches!(word.scope, Scope::String | Scope::Comment) {
+            let end = (word.col + word.text.chars().count()).min(line.len());
+            is_code[word.col.min(end)..end].fill(false);
+        }
+    }
+    line.iter()
+        .enumerate()
+        .filter(|&(col, &ch)| is_code[col] && pair(ch).is_some())
+        .map(|(col, &ch)| (col, ch))
+        .collect()
+}
+
+/// Brackets on `buf[row]`, or none if the row wasn't lexed
+fn brackets_in(lexed: &LexedRows, buf: &[Vec<char>], row: usize) -> Vec<(usize, char)> {
+    lexed
+        .words(row)
+        .map(|words| brackets(&buf[row], words))
+        .unwrap_or_default()
+}
+
+/// Finds the bracket matching the one at `(col, row)`
+pub fn find_match(
+    lexed: &LexedRows,
+    buf: &[Vec<char>],
+    (col, row): (usize, usize),
+) -> Option<(usize, usize)> {
+    let row_brackets = brackets_in(lexed, buf, row);
+    let &(_, ch) = row_brackets.iter().find(|&&(c, _)| c == col)?;
+    let (open, close) = pair(ch)?;
+    let forward = is_open(ch);
+
+    let rows: Box<dyn Iterator<Item = usize>> = if forward {
+        Box::new(row..buf.len().min(row + MAX_SCAN_ROWS))
+    } else {
+        Box::new((row.saturating_sub(MAX_SCAN_ROWS)..=row).rev())
+    };
+    let mut depth = 0;
+    for r in rows {
+        let mut line_brackets = if r == row {
+            row_brackets.clone()
+        } else {
+            brackets_in(lexed, buf, r)
+        };
+        if !forward {
+            line_brackets.reverse();
+        }
+        for (c, b) in line_brackets {
+            if r == row && (if forward { c < col } else { c > col }) {
+                continue;
+            }
+            if b == ch {
+                depth += 1;
+            } else if b == if forward { close } else { open } {
+                depth -= 1;
+                if depth == 0 {
+                    return Some((c, r));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// How deeply nested the brackets are at the start of `row`
+pub fn depth_at(lexed: &LexedRows, buf: &[Vec<char>], row: usize) -> usize {
+    let mut depth = 0usize;
+    for r in row.saturating_sub(MAX_SCAN_ROWS).max(lexed.rows().start)..row {
+        for (_, ch) in brackets_in(lexed, buf, r) {
+            if is_open(ch) {
+                depth += 1;
+            } else {
+                depth = depth.saturating_sub(1);
+            }
+        }
+    }
+    depth
+}
+
+/// The character that closes `open` in the language's auto-pairs
+pub fn closing(language: &dyn Language, open: char) -> Option<char> {
+    language
+        .auto_pairs()
+        .iter()
+        .find(|&&(o, _)| o == open)
+        .map(|&(_, close)| close)
+}
+
+pub fn is_closing(language: &dyn Language, ch: char) -> bool {
+    language.auto_pairs().iter().any(|&(_, close)| close == ch)
+}
+
+/// Whether typing `open` at `col` should also insert its closing character. This only happens
+/// in front of whitespace or another closing character so that typing before a word doesn't
+/// leave a stray closer, and quotes aren't paired right after a word (e.g. `don't`).
+pub fn should_auto_pair(language: &dyn Language, line: &[char], col: usize, open: char) -> bool {
+    let Some(close) = closing(language, open) else {
+        return false;
+    };
+    if line
+        .get(col)
+        .is_some_and(|&next| !next.is_whitespace() && !is_closing(language, next))
+    {
+        return false;
+    }
+    if open == close && col > 0 && line[col - 1].is_alphanumeric() {
+        return false;
+    }
+    language.should_pair(line, col, open)
+}
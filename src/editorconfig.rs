@@ -0,0 +1,246 @@
+use crate::options::Options;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// The settings `.editorconfig` files give a single file. See <https://editorconfig.org>.
+#[derive(Debug, Default, Clone)]
+pub struct EditorConfig {
+    properties: HashMap<String, String>,
+}
+
+impl EditorConfig {
+    /// Collects the properties for `file_path` from the `.editorconfig` files in its directory
+    /// and the ones above it, up to the first one with `root = true`
+    pub fn load(file_path: &str) -> Self {
+        let mut config = Self::default();
+        let Ok(path) = std::path::absolute(file_path) else {
+            return config;
+        };
+        let path = path.to_string_lossy().chars().collect::<Vec<_>>();
+
+        let mut files = Vec::new();
+        for dir in Path::new(file_path)
+            .parent()
+            .and_then(|dir| std::path::absolute(dir).ok())
+            .iter()
+            .flat_map(|dir| dir.ancestors())
+        {
+            let Ok(src) = std::fs::read_to_string(dir.join(".editorconfig")) else {
+                continue;
+            };
+            let is_root = src
+                .lines()
+                .take_while(|line| !is_section(line))
+                .any(|line| {
+                    parse_property(line)
+                        .is_some_and(|(key, value)| key == "root" && value == "true")
+                });
+            files.push((dir.to_string_lossy().into_owned(), src));
+            if is_root {
+                break;
+            }
+        }
+
+        // Closer files take precedence, so they're applied last
+        for (dir, src) in files.iter().rev() {
+            config.apply_file(dir, src, &path);
+        }
+        config
+    }
+
+    fn apply_file(&mut self, dir: &str, src: &str, path: &[char]) {
+        let Some(relative) = path
+            .strip_prefix(dir.chars().collect::<Vec<_>>().as_slice())
+            .and_then(|rest| rest.strip_prefix(&['/']).or(Some(rest)))
+        else {
+            return;
+        };
+
+        let mut matches = false;
+        for line in src.lines() {
+            let line = line.trim();
+            if is_section(line) {
+                let glob = &line[1..line.rfind(']').unwrap_or(line.len())];
+                matches = section_matches(glob, relative);
+            } else if let Some((key, value)) = parse_property(line).filter(|_| matches) {
+                if value == "unset" {
+                    self.properties.remove(&key);
+                } else {
+                    self.properties.insert(key, value);
+                }
+            }
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.properties.get(key).map(String::as_str)
+    }
+
+    fn number(&self, key: &str) -> Option<usize> {
+        self.get(key)?.parse().ok().filter(|&n| n > 0)
+    }
+
+    fn bool(&self, key: &str) -> Option<bool> {
+        match self.get(key)? {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Overrides the options that have a matching property
+    pub fn apply(&self, options: &mut Options) {
+        match self.get("indent_style") {
+            Some("tab") => options.hard_tabs = Some(true),
+            Some("space") => options.hard_tabs = Some(false),
+            _ => {}
+        }
+        // `tab_width` defaults to `indent_size` and `indent_size = tab` means `tab_width`
+        if let Some(width) = self.number("tab_width").or(self.number("indent_size")) {
+            options.tab_width = width;
+        }
+        if let Some(width) = self.number("indent_size") {
+            options.indent_width = width;
+        } else if self.get("indent_size") == Some("tab") {
+            options.indent_width = options.tab_width;
+        }
+        if let Some(trim) = self.bool("trim_trailing_whitespace") {
+            options.trim_trailing_whitespace = trim;
+        }
+        if let Some(insert) = self.bool("insert_final_newline") {
+            options.insert_final_newline = Some(insert);
+        }
+        if let Some(max) = self.get("max_line_length") {
+            options.max_line_length = max.parse().ok();
+        }
+    }
+}
+
+fn is_section(line: &str) -> bool {
+    line.trim_start().starts_with('[')
+}
+
+/// Parses a `key = value` line. Keys and values are case-insensitive.
+fn parse_property(line: &str) -> Option<(String, String)> {
+    let line = line.trim();
+    if line.starts_with(['#', ';']) {
+        return None;
+    }
+    let (key, value) = line.split_once('=')?;
+    Some((key.trim().to_lowercase(), value.trim().to_lowercase()))
+}
+
+/// Checks if a section glob matches `relative`, the path of a file relative to the directory of
+/// the `.editorconfig` file. Globs without a slash match files in any subdirectory.
+fn section_matches(glob: &str, relative: &[char]) -> bool {
+    let anchored = glob.contains('/');
+    let glob = glob.strip_prefix('/').unwrap_or(glob);
+    expand_braces(glob).iter().any(|pattern| {
+        let pattern = pattern.chars().collect::<Vec<_>>();
+        if anchored {
+            return glob_match(&pattern, relative);
+        }
+        std::iter::once(0)
+            .chain(
+                (0..relative.len())
+                    .filter(|&i| relative[i] == '/')
+                    .map(|i| i + 1),
+            )
+            .any(|start| glob_match(&pattern, &relative[start..]))
+    })
+}
+
+/// Expands `{a,b}` and `{1..3}` into separate patterns. Braces without a comma or range are
+/// left as they are.
+fn expand_braces(pattern: &str) -> Vec<String> {
+    let chars = pattern.chars().collect::<Vec<_>>();
+    for open in (0..chars.len()).filter(|&i| chars[i] == '{') {
+        let mut depth = 0;
+        let Some(close) = (open..chars.len()).find(|&i| {
+            match chars[i] {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                _ => {}
+            }
+            depth == 0
+        }) else {
+            break;
+        };
+
+        let mut alternatives = Vec::new();
+        let mut start = open + 1;
+        let mut depth = 0;
+        for i in open + 1..close {
+            match chars[i] {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                ',' if depth == 0 => {
+                    alternatives.push(chars[start..i].iter().collect::<String>());
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        let last = chars[start..close].iter().collect::<String>();
+        if alternatives.is_empty() {
+            let range = last
+                .split_once("..")
+                .and_then(|(from, to)| Some((from.parse::<i64>().ok()?, to.parse::<i64>().ok()?)));
+            let Some((from, to)) = range else {
+                continue;
+            };
+            alternatives = (from.min(to)..=from.max(to))
+                .map(|n| n.to_string())
+                .collect();
+        } else {
+            alternatives.push(last);
+        }
+
+        let prefix = chars[..open].iter().collect::<String>();
+        let suffix = chars[close + 1..].iter().collect::<String>();
+        return alternatives
+            .iter()
+            .flat_map(|alternative| expand_braces(&format!("{prefix}{alternative}{suffix}")))
+            .collect();
+    }
+    vec![pattern.to_string()]
+}
+
+/// Matches `path` against a glob with `*`, `**`, `?` and `[...]`
+fn glob_match(pattern: &[char], path: &[char]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        ['*', '*', rest @ ..] => (0..=path.len()).any(|i| glob_match(rest, &path[i..])),
+        ['*', rest @ ..] => (0..=path.len())
+            .take_while(|&i| i == 0 || path[i - 1] != '/')
+            .any(|i| glob_match(rest, &path[i..])),
+        ['?', rest @ ..] => {
+            path.first().is_some_and(|&ch| ch != '/') && glob_match(rest, &path[1..])
+        }
+        ['[', class @ ..] if class.contains(&']') => {
+            let end = class.iter().position(|&ch| ch == ']').unwrap();
+            let (negated, set) = match &class[..end] {
+                ['!', set @ ..] => (true, set),
+                set => (false, set),
+            };
+            let Some(&ch) = path.first().filter(|&&ch| ch != '/') else {
+                return false;
+            };
+            let mut in_set = false;
+            let mut i = 0;
+            while i < set.len() {
+                if set.get(i + 1) == Some(&'-') && i + 2 < set.len() {
+                    in_set |= (set[i]..=set[i + 2]).contains(&ch);
+                    i += 3;
+                } else {
+                    in_set |= set[i] == ch;
+                    i += 1;
+                }
+            }
+            in_set != negated && glob_match(&class[end + 1..], &path[1..])
+        }
+        ['\\', escaped, rest @ ..] | [escaped, rest @ ..] => {
+            path.first() == Some(escaped) && glob_match(rest, &path[1..])
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+
+/// How the text of a buffer is stored on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileEncoding {
+    pub encoding: &'static Encoding,
+    /// Whether the file starts with a byte order mark
+    pub bom: bool,
+}
+
+impl Default for FileEncoding {
+    fn default() -> Self {
+        Self {
+            encoding: UTF_8,
+            bom: false,
+        }
+    }
+}
+
+impl FileEncoding {
+    /// Looks up an encoding by the names `.editorconfig` uses (`utf-8-bom`, `latin1`...) or any
+    /// of the labels browsers know (`shift_jis`, `iso-8859-2`...)
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        let (label, bom) = match name.strip_suffix("-bom") {
+            Some(label) => (label, true),
+            None => (name.as_str(), false),
+        };
+        let encoding = Encoding::for_label(label.as_bytes())?;
+        // UTF-16 files always start with a BOM, since nothing can tell the byte order otherwise
+        let bom = bom || encoding == UTF_16LE || encoding == UTF_16BE;
+        Some(Self { encoding, bom })
+    }
+
+    /// The name shown in the status line
+    pub fn name(self) -> String {
+        let name = if self.encoding == WINDOWS_1252 {
+            // What `latin1` and `iso-8859-1` actually mean
+            "Latin-1"
+        } else {
+            self.encoding.name()
+        };
+        if self.bom && self.encoding == UTF_8 {
+            format!("{name} BOM")
+        } else {
+            name.into()
+        }
+    }
+
+    /// Guesses the encoding of a file from its byte order mark or, failing that, its contents
+    pub fn detect(bytes: &[u8]) -> Self {
+        if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+            return Self {
+                encoding,
+                bom: true,
+            };
+        }
+        if std::str::from_utf8(bytes).is_ok() {
+            return Self::default();
+        }
+        let mut detector = chardetng::EncodingDetector::new();
+        detector.feed(bytes, true);
+        Self {
+            encoding: detector.guess(None, true),
+            bom: false,
+        }
+    }
+
+    /// Decodes `bytes`, replacing invalid sequences with U+FFFD. Also returns whether there
+    /// were any.
+    pub fn decode(self, bytes: &[u8]) -> (String, bool) {
+        let (text, lossy) = self.encoding.decode_with_bom_removal(bytes);
+        (text.into_owned(), lossy)
+    }
+
+    /// Encodes `text`, or returns `None` if it has characters the encoding can't represent
+    pub fn encode(self, text: &str) -> Option<Vec<u8>> {
+        // encoding_rs only decodes UTF-16, so it has to be encoded by hand
+        if self.encoding == UTF_16LE || self.encoding == UTF_16BE {
+            let to_bytes = if self.encoding == UTF_16LE {
+                u16::to_le_bytes
+            } else {
+                u16::to_be_bytes
+            };
+            let bom = self.bom.then_some('\u{feff}');
+            let units = bom.into_iter().chain(text.chars()).collect::<String>();
+            return Some(units.encode_utf16().flat_map(to_bytes).collect());
+        }
+
+        let (bytes, _, unmappable) = self.encoding.encode(text);
+        if unmappable {
+            return None;
+        }
+        let bom: &[u8] = if self.bom && self.encoding == UTF_8 {
+            &[0xef, 0xbb, 0xbf]
+        } else {
+            &[]
+        };
+        Some([bom, &bytes].concat())
+    }
+}
@@ -0,0 +1,44 @@
+use std::fs::Metadata;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// How often the open file is checked for changes made by other programs
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What a file looked like on disk when it was last loaded or saved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+    hash: u64,
+}
+
+impl FileStamp {
+    pub fn new(metadata: &Metadata, bytes: &[u8]) -> Self {
+        let mut hasher = DefaultHasher::new();
+        bytes.hash(&mut hasher);
+        Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+            hash: hasher.finish(),
+        }
+    }
+
+    /// Updates the stamp to the file at `path` as it is now and returns whether its contents
+    /// changed. The file is only read when its modification time or size changed.
+    pub fn refresh(&mut self, path: &Path) -> bool {
+        let Ok(metadata) = std::fs::metadata(path) else {
+            return false;
+        };
+        if metadata.modified().ok() == self.modified && metadata.len() == self.len {
+            return false;
+        }
+        let Ok(bytes) = std::fs::read(path) else {
+            return false;
+        };
+        let old = std::mem::replace(self, Self::new(&metadata, &bytes));
+        // Touching a file or writing back the same contents isn't a change
+        self.hash != old.hash || self.len != old.len
+    }
+}
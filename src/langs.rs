@@ -1,19 +1,226 @@
 #![allow(clippy::type_complexity)]
 
-use crossterm::style::{Attribute, Color};
+use std::ops::Range;
+
+/// What a piece of text is, so that the theme can decide how it looks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Text,
+    Keyword,
+    Operator,
+    Constant,
+    Type,
+    Function,
+    Macro,
+    Identifier,
+    /// Keys in data languages like JSON and YAML
+    Property,
+    /// Variables that get expanded, like `$HOME` in shell scripts
+    Variable,
+    String,
+    Number,
+    Comment,
+    /// Escapes, regular expressions, anchors and the like
+    Special,
+    Tag,
+    /// Tokens that aren't valid in the language
+    Invalid,
+    /// Mistakes that are hard to see otherwise, like spaces instead of tabs in a Makefile
+    Error,
+    Heading,
+    Strong,
+    Emphasis,
+    Link,
+    Quote,
+    /// List bullets and similar markup
+    Marker,
+    /// Markup that only delimits something else, like code fences
+    Delimiter,
+    DiffAdded,
+    DiffRemoved,
+    DiffHunk,
+    DiffMeta,
+}
+
+impl Scope {
+    /// The name of the scope in theme files
+    pub fn name(self) -> &'static str {
+        match self {
+            Scope::Text => "text",
+            Scope::Keyword => "keyword",
+            Scope::Operator => "operator",
+            Scope::Constant => "constant",
+            Scope::Type => "type",
+            Scope::Function => "function",
+            Scope::Macro => "macro",
+            Scope::Identifier => "identifier",
+            Scope::Property => "property",
+            Scope::Variable => "variable",
+            Scope::String => "string",
+            Scope::Number => "number",
+            Scope::Comment => "comment",
+            Scope::Special => "special",
+            Scope::Tag => "tag",
+            Scope::Invalid => "invalid",
+            Scope::Error => "error",
+            Scope::Heading => "markup.heading",
+            Scope::Strong => "markup.strong",
+            Scope::Emphasis => "markup.emphasis",
+            Scope::Link => "markup.link",
+            Scope::Quote => "markup.quote",
+            Scope::Marker => "markup.marker",
+            Scope::Delimiter => "markup.delimiter",
+            Scope::DiffAdded => "diff.added",
+            Scope::DiffRemoved => "diff.removed",
+            Scope::DiffHunk => "diff.hunk",
+            Scope::DiffMeta => "diff.meta",
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Word {
     pub col: usize,
     pub text: String,
-    pub color: Color,
-    pub attr: Attribute,
+    pub scope: Scope,
+}
+
+/// Adds a word to the ones a lexer found, unless it's empty
+pub(super) fn push_word(words: &mut Vec<Word>, col: usize, text: &[char], scope: Scope) {
+    if text.is_empty() {
+        return;
+    }
+    words.push(Word {
+        col,
+        text: text.iter().collect(),
+        scope,
+    });
+}
+
+/// How many whitespace characters `line` starts with
+pub(super) fn get_indent(line: &[char]) -> usize {
+    line.iter()
+        .take_while(|ch| ch.is_ascii_whitespace())
+        .count()
+}
+
+/// How many spaces `line` starts with, for languages where tabs aren't indentation
+pub(super) fn get_space_indent(line: &[char]) -> usize {
+    line.iter().take_while(|&&ch| ch == ' ').count()
+}
+
+/// The words and signs of a range of rows, kept between frames so that rendering only lexes the
+/// buffer again after it changes
+pub struct LexedRows {
+    /// The buffer and language the rows were lexed with, to tell when they're out of date
+    pub hash: u64,
+    pub language: String,
+    start: usize,
+    words: Vec<Vec<Word>>,
+    signs: Vec<Option<(char, Scope)>>,
 }
 
+impl LexedRows {
+    pub fn new(
+        language: &dyn Language,
+        language_name: String,
+        buf: &[Vec<char>],
+        hash: u64,
+        rows: Range<usize>,
+    ) -> Self {
+        Self {
+            hash,
+            language: language_name,
+            start: rows.start,
+            words: language.split_rows_in(buf, rows.clone()),
+            signs: language.signs_in(buf, rows),
+        }
+    }
+
+    pub fn rows(&self) -> Range<usize> {
+        self.start..self.start + self.words.len()
+    }
+
+    pub fn words(&self, row: usize) -> Option<&[Word]> {
+        self.words
+            .get(row.checked_sub(self.start)?)
+            .map(Vec::as_slice)
+    }
+
+    pub fn sign(&self, row: usize) -> Option<(char, Scope)> {
+        *self.signs.get(row.checked_sub(self.start)?)?
+    }
+}
+
+/// Pairs that are closed automatically when typing the opening character
+pub const AUTO_PAIRS: &[(char, char)] =
+    &[('(', ')'), ('[', ']'), ('{', '}'), ('"', '"'), ('\'', '\'')];
+
 pub trait Language {
     fn split_words(&self, code: &[char]) -> Vec<Word>;
     fn should_indent(&self, line: &[char]) -> bool;
     fn should_dedent(&self, ch: char) -> bool;
+
+    /// Same as `split_words`, but for languages that need to look at the surrounding lines
+    /// (e.g. fenced code blocks in Markdown)
+    fn split_words_in(&self, buf: &[Vec<char>], row: usize) -> Vec<Word> {
+        self.split_words(&buf[row])
+    }
+
+    /// Same as `split_words_in` for each of `rows`. Languages whose rows depend on the ones
+    /// before them override this to work that out once instead of for every row.
+    fn split_rows_in(&self, buf: &[Vec<char>], rows: Range<usize>) -> Vec<Vec<Word>> {
+        rows.map(|row| self.split_words_in(buf, row)).collect()
+    }
+
+    /// Text to insert after the indentation when pressing Enter at the end of `line`
+    /// (e.g. the next list bullet in Markdown)
+    fn line_continuation(&self, _line: &[char]) -> Option<Vec<char>> {
+        None
+    }
+
+    /// Whether indentation must use tab characters instead of spaces (e.g. Makefile recipes)
+    fn hard_tabs(&self) -> bool {
+        false
+    }
+
+    /// What starts a comment that runs until the end of the line (e.g. `//` in Rust). Gets the
+    /// buffer since some languages let the file choose, like git's comment character.
+    fn line_comment(&self, _buf: &[Vec<char>]) -> Option<String> {
+        None
+    }
+
+    /// The delimiters of comments that can span several lines (e.g. `/*` and `*/`)
+    fn block_comment(&self) -> Option<(&'static str, &'static str)> {
+        None
+    }
+
+    /// Characters whose closing counterpart gets inserted when they're typed
+    fn auto_pairs(&self) -> &'static [(char, char)] {
+        AUTO_PAIRS
+    }
+
+    /// Whether typing `open` at `col` in `line` should insert its closing character, for
+    /// exceptions that depend on the context (e.g. `'` starting a lifetime in Rust)
+    fn should_pair(&self, _line: &[char], _col: usize, _open: char) -> bool {
+        true
+    }
+
+    /// A marker to show in the sign column next to `buf[row]`, e.g. for lines with mistakes
+    fn sign(&self, _buf: &[Vec<char>], _row: usize) -> Option<(char, Scope)> {
+        None
+    }
+
+    /// Same as `sign` for each of `rows`
+    fn signs_in(&self, buf: &[Vec<char>], rows: Range<usize>) -> Vec<Option<(char, Scope)>> {
+        rows.map(|row| self.sign(buf, row)).collect()
+    }
+
+    /// Checks the whole buffer for syntax errors. Returns `None` if the language doesn't
+    /// support validation.
+    fn validate(&self, _buf: &[Vec<char>]) -> Option<Result<(), SyntaxError>> {
+        None
+    }
 }
 
 impl<T: Language + ?Sized> Language for &T {
@@ -21,6 +228,50 @@ impl<T: Language + ?Sized> Language for &T {
         (**self).split_words(code)
     }
 
+    fn split_words_in(&self, buf: &[Vec<char>], row: usize) -> Vec<Word> {
+        (**self).split_words_in(buf, row)
+    }
+
+    fn split_rows_in(&self, buf: &[Vec<char>], rows: Range<usize>) -> Vec<Vec<Word>> {
+        (**self).split_rows_in(buf, rows)
+    }
+
+    fn line_continuation(&self, line: &[char]) -> Option<Vec<char>> {
+        (**self).line_continuation(line)
+    }
+
+    fn hard_tabs(&self) -> bool {
+        (**self).hard_tabs()
+    }
+
+    fn line_comment(&self, buf: &[Vec<char>]) -> Option<String> {
+        (**self).line_comment(buf)
+    }
+
+    fn block_comment(&self) -> Option<(&'static str, &'static str)> {
+        (**self).block_comment()
+    }
+
+    fn auto_pairs(&self) -> &'static [(char, char)] {
+        (**self).auto_pairs()
+    }
+
+    fn should_pair(&self, line: &[char], col: usize, open: char) -> bool {
+        (**self).should_pair(line, col, open)
+    }
+
+    fn sign(&self, buf: &[Vec<char>], row: usize) -> Option<(char, Scope)> {
+        (**self).sign(buf, row)
+    }
+
+    fn signs_in(&self, buf: &[Vec<char>], rows: Range<usize>) -> Vec<Option<(char, Scope)>> {
+        (**self).signs_in(buf, rows)
+    }
+
+    fn validate(&self, buf: &[Vec<char>]) -> Option<Result<(), SyntaxError>> {
+        (**self).validate(buf)
+    }
+
     fn should_indent(&self, line: &[char]) -> bool {
         (**self).should_indent(line)
     }
@@ -30,37 +281,199 @@ impl<T: Language + ?Sized> Language for &T {
     }
 }
 
-pub const fn rgb_color(r: u8, g: u8, b: u8) -> Color {
-    Color::Rgb { r, g, b }
+#[derive(Debug, Clone)]
+pub struct SyntaxError {
+    pub line: usize,
+    pub col: usize,
+    pub msg: String,
+}
+
+/// Walks over a buffer character by character for the validators, yielding `'\n'` between rows.
+pub struct Scanner<'a> {
+    buf: &'a [Vec<char>],
+    pub line: usize,
+    pub col: usize,
+}
+
+impl<'a> Scanner<'a> {
+    pub fn new(buf: &'a [Vec<char>]) -> Self {
+        Self {
+            buf,
+            line: 0,
+            col: 0,
+        }
+    }
+
+    pub fn peek(&self) -> Option<char> {
+        self.peek_nth(0)
+    }
+
+    pub fn peek_nth(&self, n: usize) -> Option<char> {
+        let row = self.buf.get(self.line)?;
+        match (self.col + n).cmp(&row.len()) {
+            std::cmp::Ordering::Less => Some(row[self.col + n]),
+            std::cmp::Ordering::Equal if self.line + 1 < self.buf.len() => Some('\n'),
+            _ => None,
+        }
+    }
+
+    pub fn bump(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        if ch == '\n' {
+            self.line += 1;
+            self.col = 0;
+        } else {
+            self.col += 1;
+        }
+        Some(ch)
+    }
+
+    pub fn eat(&mut self, ch: char) -> bool {
+        if self.peek() == Some(ch) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn eat_str(&mut self, s: &str) -> bool {
+        if !s
+            .chars()
+            .enumerate()
+            .all(|(i, ch)| self.peek_nth(i) == Some(ch))
+        {
+            return false;
+        }
+        for _ in s.chars() {
+            self.bump();
+        }
+        true
+    }
+
+    /// The text of the current line from column `start` up to the scanner
+    pub fn text_from(&self, start: usize) -> String {
+        self.buf
+            .get(self.line)
+            .map(|row| {
+                row[start.min(self.col)..self.col.min(row.len())]
+                    .iter()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn error(&self, msg: impl Into<String>) -> SyntaxError {
+        SyntaxError {
+            line: self.line,
+            col: self.col,
+            msg: msg.into(),
+        }
+    }
 }
 
 macro_rules! extension {
-    ($ext:literal) => {
-        |name| name.split('.').last().filter(|ext| *ext == $ext).is_some()
+    ($($ext:literal),+) => {
+        |name: &str| {
+            name.split('.')
+                .last()
+                .filter(|ext| matches!(*ext, $($ext)|+))
+                .is_some()
+        }
     };
 }
 
 macro_rules! exact {
-    ($name:literal) => {
+    ($($name:literal),+) => {
         // TODO: windows strikes again
-        |name| {
+        |name: &str| {
+            name.split('/')
+                .last()
+                .filter(|name| matches!(*name, $($name)|+))
+                .is_some()
+        }
+    };
+}
+
+macro_rules! prefix {
+    ($prefix:literal) => {
+        |name: &str| {
             name.split('/')
                 .last()
-                .filter(|name| *name == $name)
+                .filter(|name| name.starts_with($prefix))
                 .is_some()
         }
     };
 }
 
-mod commit;
+pub mod commit;
+pub mod diff;
+mod dockerfile;
+mod javascript;
+mod json;
+mod makefile;
+mod markdown;
 mod plaintext;
 mod python;
 mod rust;
+mod shell;
+mod toml;
+mod yaml;
 
 const LANGS: &[(&str, fn(&str) -> bool, &dyn Language)] = &[
     ("rust", extension!("rs"), &rust::Rust),
     ("python", extension!("py"), &python::Python),
-    ("git-commit", exact!("COMMIT_EDITMSG"), &commit::Commit),
+    (
+        "javascript",
+        extension!("js", "mjs", "cjs", "jsx"),
+        &javascript::JavaScript,
+    ),
+    (
+        "typescript",
+        extension!("ts", "mts", "cts", "tsx"),
+        &javascript::TypeScript,
+    ),
+    ("json", extension!("json"), &json::Json),
+    ("toml", extension!("toml"), &toml::Toml),
+    ("yaml", extension!("yaml", "yml"), &yaml::Yaml),
+    (
+        "markdown",
+        extension!("md", "markdown"),
+        &markdown::Markdown,
+    ),
+    (
+        "shell",
+        |name| {
+            extension!("sh", "bash", "zsh", "ksh")(name)
+                || exact!(".bashrc", ".bash_profile", ".profile", ".zshrc")(name)
+        },
+        &shell::Shell,
+    ),
+    (
+        "makefile",
+        |name| exact!("Makefile", "makefile", "GNUmakefile")(name) || extension!("mk")(name),
+        &makefile::Makefile,
+    ),
+    (
+        "dockerfile",
+        |name| {
+            exact!("Dockerfile", "Containerfile")(name)
+                || prefix!("Dockerfile.")(name)
+                || extension!("dockerfile")(name)
+        },
+        &dockerfile::Dockerfile,
+    ),
+    ("diff", extension!("diff", "patch"), &diff::Diff),
+    (
+        "git-commit",
+        exact!("COMMIT_EDITMSG", "MERGE_MSG", "TAG_EDITMSG"),
+        &commit::Commit,
+    ),
+    (
+        "git-rebase-todo",
+        exact!("git-rebase-todo"),
+        &commit::RebaseTodo,
+    ),
     ("plaintext", |_| true, &plaintext::Plaintext),
 ];
 pub const DEFAULT_LANG: &str = "plaintext";
@@ -78,3 +491,44 @@ pub fn lang_from_filename(name: &str) -> Option<&'static dyn Language> {
         .find(|(_, validator, _)| validator(name))
         .map(|(_, _, lang)| *lang)
 }
+
+/// Guesses the language of a script from its `#!` line
+fn lang_name_from_shebang(line: &[char]) -> Option<&'static str> {
+    let line = line.iter().collect::<String>();
+    let mut args = line.strip_prefix("#!")?.split_whitespace();
+    let mut interpreter = args.next()?.rsplit('/').next()?;
+    if interpreter == "env" {
+        interpreter = args.find(|arg| !arg.starts_with('-') && !arg.contains('='))?;
+    }
+    // python3.12 -> python
+    let interpreter = interpreter.trim_end_matches(|ch: char| ch.is_ascii_digit() || ch == '.');
+
+    let name = match interpreter {
+        "sh" | "bash" | "dash" | "zsh" | "ksh" | "ash" | "mksh" => "shell",
+        "python" | "pypy" => "python",
+        "node" | "nodejs" | "deno" | "bun" => "javascript",
+        "ts-node" | "tsx" => "typescript",
+        "make" | "gmake" => "makefile",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Detects the language of a file from its name, falling back to the shebang on its first line
+/// for files without a recognizable extension. Returns the name of the language along with it.
+pub fn lang_from_file(name: &str, first_line: &[char]) -> (&'static str, &'static dyn Language) {
+    LANGS
+        .iter()
+        .filter(|(lang_name, _, _)| *lang_name != DEFAULT_LANG)
+        .find(|(_, validator, _)| validator(name))
+        .map(|(lang_name, _, _)| *lang_name)
+        .or_else(|| lang_name_from_shebang(first_line))
+        .or_else(|| {
+            // Output of `git diff` or `diff -u` saved without an extension
+            let line = first_line.iter().collect::<String>();
+            (line.starts_with("diff ") || line.starts_with("--- ")).then_some("diff")
+        })
+        .and_then(|name| Some((name, lang_from_name(name)?)))
+        .or_else(|| Some((DEFAULT_LANG, lang_from_name(DEFAULT_LANG)?)))
+        .expect("default language should exist")
+}
@@ -1,23 +1,182 @@
+use super::{diff, shell};
 use crate::*;
+use std::sync::OnceLock;
+
+const SUBJECT_LIMIT: usize = 50;
+const BODY_LIMIT: usize = 72;
+
+/// What follows the comment character on the line that `git commit --verbose` puts above the diff
+const SCISSORS: &str = " ------------------------ >8 ------------------------";
+
+/// Actions of a rebase todo line that take a commit, in the order `rebase-cycle` goes through them
+const CYCLE: &[&str] = &["pick", "reword", "edit", "squash", "fixup", "drop"];
+
+/// All rebase todo actions with their abbreviations
+const ACTIONS: &[(&str, &str)] = &[
+    ("pick", "p"),
+    ("reword", "r"),
+    ("edit", "e"),
+    ("squash", "s"),
+    ("fixup", "f"),
+    ("drop", "d"),
+    ("exec", "x"),
+    ("break", "b"),
+    ("label", "l"),
+    ("reset", "t"),
+    ("merge", "m"),
+    ("update-ref", "u"),
+    ("noop", "noop"),
+];
+
+fn comment(line: &[char]) -> Vec<Word> {
+    let mut words = Vec::new();
+    push_word(&mut words, 0, line, Scope::Comment);
+    words
+}
+
+/// `core.commentChar` from the git config, or `#` if it isn't set or git isn't available
+fn configured_comment_char() -> char {
+    static COMMENT_CHAR: OnceLock<char> = OnceLock::new();
+    *COMMENT_CHAR.get_or_init(|| {
+        std::process::Command::new("git")
+            .args(["config", "--get", "core.commentChar"])
+            .output()
+            .ok()
+            .and_then(|output| String::from_utf8(output.stdout).ok())
+            .and_then(|value| {
+                let value = value.trim();
+                // With `auto`, git picks a character that isn't used in the message
+                (value != "auto").then(|| value.chars().next()).flatten()
+            })
+            .unwrap_or('#')
+    })
+}
+
+/// The comment character of a commit message or rebase todo file. The lines git generates tell
+/// which one it used (which matters for `core.commentChar = auto`), so those are checked before
+/// the git config.
+pub fn comment_char(buf: &[Vec<char>]) -> char {
+    buf.iter()
+        .find_map(|line| {
+            let first = *line.first()?;
+            let rest = line[1..].iter().collect::<String>();
+            let generated = rest == SCISSORS
+                || rest.starts_with(" Please enter the commit message")
+                || rest.starts_with(" Rebase ")
+                || rest.starts_with(" Commands:");
+            generated.then_some(first)
+        })
+        .unwrap_or_else(configured_comment_char)
+}
+
+fn is_scissors(line: &[char], comment_char: char) -> bool {
+    line.first() == Some(&comment_char) && line[1..].iter().copied().eq(SCISSORS.chars())
+}
+
+/// What highlighting a row of a commit message needs to know about the rest of the message
+struct Layout {
+    comment_char: char,
+    /// The row of the scissors line, below which is the diff
+    scissors: Option<usize>,
+    /// Git strips comments and leading blank lines, so the subject is the first row with text
+    subject: usize,
+}
+
+impl Layout {
+    fn new(buf: &[Vec<char>]) -> Self {
+        let comment_char = comment_char(buf);
+        let scissors = buf.iter().position(|line| is_scissors(line, comment_char));
+        let subject = buf
+            .iter()
+            .position(|line| {
+                line.first() != Some(&comment_char) && line.iter().any(|ch| !ch.is_whitespace())
+            })
+            .unwrap_or(0);
+        Self {
+            comment_char,
+            scissors,
+            subject,
+        }
+    }
+}
+
+/// Highlights the part of `line` past `limit` characters as an error
+fn limited(line: &[char], limit: usize, scope: Scope) -> Vec<Word> {
+    let split = line.len().min(limit);
+    let mut words = Vec::new();
+    push_word(&mut words, 0, &line[..split], scope);
+    push_word(&mut words, split, &line[split..], Scope::Invalid);
+    words
+}
+
+/// Marks rows with mistakes, like a subject that's too long
+fn sign(words: &[Word]) -> Option<(char, Scope)> {
+    words
+        .iter()
+        .any(|word| matches!(word.scope, Scope::Invalid | Scope::Error))
+        .then_some(('!', Scope::Invalid))
+}
 
 pub struct Commit;
 
+impl Commit {
+    fn split_row(&self, buf: &[Vec<char>], row: usize, layout: &Layout) -> Vec<Word> {
+        let line = &buf[row];
+
+        // Everything below the scissors line is the diff of `git commit --verbose`
+        if let Some(scissors) = layout.scissors.filter(|&scissors| scissors < row) {
+            return diff::Diff.split_words_in(&buf[scissors + 1..], row - scissors - 1);
+        }
+
+        if line.first() == Some(&layout.comment_char) {
+            return comment(line);
+        }
+
+        let subject = layout.subject;
+        match row.cmp(&subject) {
+            std::cmp::Ordering::Less => Vec::new(),
+            std::cmp::Ordering::Equal => limited(line, SUBJECT_LIMIT, Scope::Strong),
+            // The subject must be separated from the body by a blank line
+            std::cmp::Ordering::Greater if row == subject + 1 && !line.is_empty() => {
+                let mut words = Vec::new();
+                push_word(&mut words, 0, line, Scope::Error);
+                words
+            }
+            std::cmp::Ordering::Greater => limited(line, BODY_LIMIT, Scope::Text),
+        }
+    }
+}
+
 impl Language for Commit {
     fn split_words(&self, code: &[char]) -> Vec<Word> {
-        if code.first().filter(|&&ch| ch == '#').is_some() {
-            return vec![Word {
-                col: 0,
-                text: code.iter().collect(),
-                color: rgb_color(100, 100, 100),
-                attr: Attribute::Italic,
-            }];
-        }
-        vec![Word {
-            col: 0,
-            text: code.iter().collect(),
-            color: Color::White,
-            attr: Attribute::Reset,
-        }]
+        if code.first() == Some(&'#') {
+            return comment(code);
+        }
+        limited(code, BODY_LIMIT, Scope::Text)
+    }
+
+    fn split_words_in(&self, buf: &[Vec<char>], row: usize) -> Vec<Word> {
+        self.split_row(buf, row, &Layout::new(buf))
+    }
+
+    fn split_rows_in(&self, buf: &[Vec<char>], rows: std::ops::Range<usize>) -> Vec<Vec<Word>> {
+        let layout = Layout::new(buf);
+        rows.map(|row| self.split_row(buf, row, &layout)).collect()
+    }
+
+    fn sign(&self, buf: &[Vec<char>], row: usize) -> Option<(char, Scope)> {
+        sign(&self.split_words_in(buf, row))
+    }
+
+    fn signs_in(
+        &self,
+        buf: &[Vec<char>],
+        rows: std::ops::Range<usize>,
+    ) -> Vec<Option<(char, Scope)>> {
+        self.split_rows_in(buf, rows)
+            .iter()
+            .map(|words| sign(words))
+            .collect()
     }
 
     fn should_indent(&self, _line: &[char]) -> bool {
@@ -27,4 +186,145 @@ impl Language for Commit {
     fn should_dedent(&self, _ch: char) -> bool {
         false
     }
+
+    fn line_comment(&self, buf: &[Vec<char>]) -> Option<String> {
+        Some(comment_char(buf).into())
+    }
+}
+
+/// Splits `line` into whitespace-separated tokens, returning their start and end indices
+fn tokens(line: &[char]) -> Vec<(usize, usize)> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < line.len() {
+        if line[i].is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < line.len() && !line[i].is_whitespace() {
+            i += 1;
+        }
+        tokens.push((start, i));
+    }
+    tokens
+}
+
+/// The full name of a rebase todo action, expanding abbreviations
+fn action_name(word: &str) -> Option<&'static str> {
+    ACTIONS
+        .iter()
+        .find(|(name, short)| word == *name || word == *short)
+        .map(|(name, _)| *name)
+}
+
+fn is_commit_hash(word: &[char]) -> bool {
+    word.len() >= 4 && word.iter().all(|ch| ch.is_ascii_hexdigit())
+}
+
+/// Highlights a line of a rebase todo file
+fn split_todo_line(line: &[char], comment_char: char) -> Vec<Word> {
+    let indent = line.iter().take_while(|ch| ch.is_whitespace()).count();
+    if line.get(indent) == Some(&comment_char) {
+        return comment(line);
+    }
+
+    let tokens = tokens(line);
+    let Some(&(start, end)) = tokens.first() else {
+        return Vec::new();
+    };
+    let Some(action) = action_name(&line[start..end].iter().collect::<String>()) else {
+        let mut words = Vec::new();
+        push_word(&mut words, start, &line[start..], Scope::Invalid);
+        return words;
+    };
+
+    let mut words = Vec::new();
+    push_word(&mut words, start, &line[start..end], Scope::Keyword);
+    if action == "exec" {
+        shell::lex(&line[end..], end, &mut words);
+        return words;
+    }
+
+    for &(start, end) in &tokens[1..] {
+        let token = &line[start..end];
+        if token[0] == '#' {
+            push_word(&mut words, start, &line[start..], Scope::Comment);
+            break;
+        } else if token[0] == '-' {
+            // `fixup -C`, `merge -c` and so on
+            push_word(&mut words, start, token, Scope::Special);
+        } else if is_commit_hash(token) {
+            push_word(&mut words, start, token, Scope::Number);
+            // The rest of the line is the commit subject, which git ignores
+            if CYCLE.contains(&action) {
+                break;
+            }
+        } else {
+            // Labels and refs
+            push_word(&mut words, start, token, Scope::Identifier);
+        }
+    }
+    words
+}
+
+pub struct RebaseTodo;
+
+impl Language for RebaseTodo {
+    fn split_words(&self, code: &[char]) -> Vec<Word> {
+        self.split_words_in(&[code.to_vec()], 0)
+    }
+
+    fn split_words_in(&self, buf: &[Vec<char>], row: usize) -> Vec<Word> {
+        split_todo_line(&buf[row], comment_char(buf))
+    }
+
+    fn split_rows_in(&self, buf: &[Vec<char>], rows: std::ops::Range<usize>) -> Vec<Vec<Word>> {
+        let comment_char = comment_char(buf);
+        rows.map(|row| split_todo_line(&buf[row], comment_char))
+            .collect()
+    }
+
+    fn should_indent(&self, _line: &[char]) -> bool {
+        false
+    }
+
+    fn should_dedent(&self, _ch: char) -> bool {
+        false
+    }
+
+    fn line_comment(&self, buf: &[Vec<char>]) -> Option<String> {
+        Some(comment_char(buf).into())
+    }
+
+    fn auto_pairs(&self) -> &'static [(char, char)] {
+        &[]
+    }
+}
+
+/// Replaces the action of a rebase todo line with the next (or previous) one in `CYCLE`. Returns
+/// `None` if the line doesn't pick a commit.
+pub fn cycle_action(line: &[char], forward: bool) -> Option<Vec<char>> {
+    let tokens = tokens(line);
+    let &(start, end) = tokens.first()?;
+    let action = action_name(&line[start..end].iter().collect::<String>())?;
+    let idx = CYCLE.iter().position(|&name| name == action)?;
+    let next = if forward {
+        CYCLE[(idx + 1) % CYCLE.len()]
+    } else {
+        CYCLE[(idx + CYCLE.len() - 1) % CYCLE.len()]
+    };
+
+    // `-C` and `-c` are only valid for fixup
+    let mut rest = end;
+    if let Some(&(flag_start, flag_end)) = tokens.get(1) {
+        if line[flag_start] == '-' {
+            rest = flag_end;
+        }
+    }
+
+    let mut new_line = line[..start].to_vec();
+    new_line.extend(next.chars());
+    new_line.extend_from_slice(&line[rest..]);
+    Some(new_line)
 }
@@ -0,0 +1,311 @@
+use crate::*;
+
+fn is_hunk_header(line: &[char]) -> bool {
+    line.starts_with(&['@', '@', ' '])
+}
+
+/// Lines like `diff --git a/x b/x` and `index 1234..5678 100644` that precede the file headers
+fn is_meta(line: &[char]) -> bool {
+    let line = line.iter().collect::<String>();
+    [
+        "diff ",
+        "index ",
+        "new file mode",
+        "deleted file mode",
+        "old mode",
+        "new mode",
+        "similarity index",
+        "dissimilarity index",
+        "rename from",
+        "rename to",
+        "copy from",
+        "copy to",
+        "Binary files",
+        "Only in ",
+    ]
+    .iter()
+    .any(|prefix| line.starts_with(prefix))
+}
+
+/// Checks if `buf[row]` is a `---` or `+++` file header, as opposed to a removed line starting
+/// with `--` or an added line starting with `++`
+fn is_file_header(buf: &[Vec<char>], row: usize) -> bool {
+    let line = &buf[row];
+    if line.starts_with(&['-', '-', '-', ' ']) {
+        buf.get(row + 1)
+            .is_some_and(|next| next.starts_with(&['+', '+', '+', ' ']))
+    } else if line.starts_with(&['+', '+', '+', ' ']) {
+        row > 0 && buf[row - 1].starts_with(&['-', '-', '-', ' '])
+    } else {
+        false
+    }
+}
+
+pub struct Diff;
+
+impl Language for Diff {
+    fn split_words(&self, code: &[char]) -> Vec<Word> {
+        let scope = match code.first() {
+            _ if is_hunk_header(code) => {
+                // Highlight the function context after the second `@@` differently
+                let end = code[3..]
+                    .windows(2)
+                    .position(|w| w == ['@', '@'])
+                    .map(|i| i + 5)
+                    .unwrap_or(code.len());
+                return vec![
+                    Word {
+                        col: 0,
+                        text: code[..end].iter().collect(),
+                        scope: Scope::DiffHunk,
+                    },
+                    Word {
+                        col: end,
+                        text: code[end..].iter().collect(),
+                        scope: Scope::Strong,
+                    },
+                ];
+            }
+            _ if is_meta(code) => Scope::DiffMeta,
+            Some('+') => Scope::DiffAdded,
+            Some('-') => Scope::DiffRemoved,
+            Some('\\') => Scope::Comment,
+            _ => Scope::Text,
+        };
+        vec![Word {
+            col: 0,
+            text: code.iter().collect(),
+            scope,
+        }]
+    }
+
+    fn split_words_in(&self, buf: &[Vec<char>], row: usize) -> Vec<Word> {
+        if is_file_header(buf, row) {
+            return vec![Word {
+                col: 0,
+                text: buf[row].iter().collect(),
+                scope: Scope::Strong,
+            }];
+        }
+        self.split_words(&buf[row])
+    }
+
+    fn should_indent(&self, _line: &[char]) -> bool {
+        false
+    }
+
+    fn should_dedent(&self, _ch: char) -> bool {
+        false
+    }
+
+    fn auto_pairs(&self) -> &'static [(char, char)] {
+        &[]
+    }
+}
+
+/// Rows of all hunk headers (`@@ -1,2 +1,3 @@`) in the buffer
+pub fn hunk_headers(buf: &[Vec<char>]) -> impl DoubleEndedIterator<Item = usize> + '_ {
+    buf.iter()
+        .enumerate()
+        .filter(|(_, line)| is_hunk_header(line))
+        .map(|(row, _)| row)
+}
+
+#[derive(Debug)]
+pub struct Hunk {
+    /// The file the hunk applies to, as written in the `+++`/`---` headers
+    pub path: Option<String>,
+    /// Zero-based line numbers where the hunk starts in the old and new file
+    pub old_start: usize,
+    pub new_start: usize,
+    pub old_lines: Vec<String>,
+    pub new_lines: Vec<String>,
+}
+
+fn parse_range(range: &str) -> Option<usize> {
+    let start = range.split(',').next()?.parse::<usize>().ok()?;
+    Some(start.saturating_sub(1))
+}
+
+/// Extracts the path from a `--- a/path` or `+++ b/path` header
+fn header_path(line: &[char]) -> Option<String> {
+    let line = line[4..].iter().collect::<String>();
+    // Some tools put a timestamp after a tab
+    let path = line.split('\t').next()?.trim();
+    if path == "/dev/null" {
+        return None;
+    }
+    let path = path
+        .strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path);
+    Some(path.to_string())
+}
+
+/// Parses the hunk that contains `buf[row]`
+pub fn hunk_at(buf: &[Vec<char>], row: usize) -> Result<Hunk, String> {
+    let header = (0..=row)
+        .rev()
+        .take_while(|&i| i == row || !is_file_header(buf, i))
+        .find(|&i| is_hunk_header(&buf[i]))
+        .ok_or("the cursor isn't inside a hunk")?;
+
+    let header_text = buf[header].iter().collect::<String>();
+    let mut ranges = header_text.split_whitespace().skip(1);
+    let (Some(old), Some(new)) = (
+        ranges.next().and_then(|r| r.strip_prefix('-')),
+        ranges.next().and_then(|r| r.strip_prefix('+')),
+    ) else {
+        return Err("invalid hunk header".into());
+    };
+    let count = |range: &str| {
+        range
+            .split(',')
+            .nth(1)
+            .map_or(Some(1), |n| n.parse::<usize>().ok())
+    };
+    let (Some(old_start), Some(new_start), Some(mut old_count), Some(mut new_count)) =
+        (parse_range(old), parse_range(new), count(old), count(new))
+    else {
+        return Err("invalid hunk header".into());
+    };
+
+    let mut old_lines = Vec::new();
+    let mut new_lines = Vec::new();
+    for line in &buf[header + 1..] {
+        if old_count == 0 && new_count == 0 {
+            break;
+        }
+        let text = line.iter().skip(1).collect::<String>();
+        match line.first() {
+            // Some editors strip the trailing space of empty context lines
+            Some(' ') | None => {
+                old_lines.push(text.clone());
+                new_lines.push(text);
+                old_count = old_count.saturating_sub(1);
+                new_count = new_count.saturating_sub(1);
+            }
+            Some('-') => {
+                old_lines.push(text);
+                old_count = old_count.saturating_sub(1);
+            }
+            Some('+') => {
+                new_lines.push(text);
+                new_count = new_count.saturating_sub(1);
+            }
+            Some('\\') => {}
+            Some(_) => return Err("hunk is shorter than its header says".into()),
+        }
+    }
+
+    let path = (0..header)
+        .rev()
+        .find(|&i| is_file_header(buf, i) && buf[i].starts_with(&['+', '+', '+']))
+        .and_then(|i| header_path(&buf[i]).or_else(|| header_path(&buf[i - 1])));
+
+    Ok(Hunk {
+        path,
+        old_start,
+        new_start,
+        old_lines,
+        new_lines,
+    })
+}
+
+/// Replaces `from` with `to` in `lines`, looking for the occurrence of `from` closest to `hint`
+/// since the line numbers in the hunk header may be off if the file changed in the meantime.
+pub fn splice(
+    lines: &mut Vec<String>,
+    from: &[String],
+    to: &[String],
+    hint: usize,
+) -> Result<(), String> {
+    let pos = if from.is_empty() {
+        hint.min(lines.len())
+    } else {
+        (0..lines.len().saturating_sub(from.len() - 1))
+            .filter(|&i| lines[i..i + from.len()] == *from)
+            .min_by_key(|&i| i.abs_diff(hint))
+            .ok_or("hunk doesn't match the file")?
+    };
+    lines.splice(pos..pos + from.len(), to.iter().cloned());
+    Ok(())
+}
+
+/// Makes a unified diff of two versions of a file with the whole file as context, so that it
+/// can be read in the editor
+pub fn unified(
+    old_name: &str,
+    new_name: &str,
+    old: &[Vec<char>],
+    new: &[Vec<char>],
+) -> Vec<Vec<char>> {
+    // Only the part between the common prefix and suffix needs the expensive comparison
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    let mut lines = vec![
+        format!("--- {old_name}"),
+        format!("+++ {new_name}"),
+        format!("@@ -1,{} +1,{} @@", old.len(), new.len()),
+    ];
+    lines.extend(
+        old[..prefix]
+            .iter()
+            .map(|line| format!(" {}", String::from_iter(line))),
+    );
+
+    // Longest common subsequence of the lines, unless that would take too much memory, in which
+    // case everything in between is shown as replaced
+    let (n, m) = (old_mid.len(), new_mid.len());
+    let mut lcs = vec![vec![0u32; m + 1]; if n * m <= 4_000_000 { n + 1 } else { 0 }];
+    for i in (0..lcs.len().saturating_sub(1)).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old_mid[i] == new_mid[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        let line = if lcs.is_empty() {
+            if i < n {
+                i += 1;
+                format!("-{}", String::from_iter(&old_mid[i - 1]))
+            } else {
+                j += 1;
+                format!("+{}", String::from_iter(&new_mid[j - 1]))
+            }
+        } else if i < n && j < m && old_mid[i] == new_mid[j] {
+            i += 1;
+            j += 1;
+            format!(" {}", String::from_iter(&old_mid[i - 1]))
+        } else if j == m || (i < n && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            i += 1;
+            format!("-{}", String::from_iter(&old_mid[i - 1]))
+        } else {
+            j += 1;
+            format!("+{}", String::from_iter(&new_mid[j - 1]))
+        };
+        lines.push(line);
+    }
+
+    lines.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|line| format!(" {}", String::from_iter(line))),
+    );
+    lines
+        .into_iter()
+        .map(|line| line.chars().collect())
+        .collect()
+}
@@ -0,0 +1,125 @@
+use super::shell;
+use crate::*;
+
+fn is_instruction(word: &str) -> bool {
+    matches!(
+        word.to_ascii_uppercase().as_str(),
+        "FROM"
+            | "RUN"
+            | "CMD"
+            | "LABEL"
+            | "MAINTAINER"
+            | "EXPOSE"
+            | "ENV"
+            | "ADD"
+            | "COPY"
+            | "ENTRYPOINT"
+            | "VOLUME"
+            | "USER"
+            | "WORKDIR"
+            | "ARG"
+            | "ONBUILD"
+            | "STOPSIGNAL"
+            | "HEALTHCHECK"
+            | "SHELL"
+    )
+}
+
+fn is_continued(line: &[char]) -> bool {
+    line.iter().rev().find(|ch| !ch.is_whitespace()) == Some(&'\\')
+}
+
+fn is_comment(line: &[char]) -> bool {
+    line.iter().find(|ch| !ch.is_whitespace()) == Some(&'#')
+}
+
+pub struct Dockerfile;
+
+impl Language for Dockerfile {
+    fn split_words(&self, code: &[char]) -> Vec<Word> {
+        let mut words = Vec::new();
+
+        let indent = code.iter().take_while(|ch| ch.is_whitespace()).count();
+        if is_comment(code) {
+            push_word(&mut words, indent, &code[indent..], Scope::Comment);
+            return words;
+        }
+
+        let line = &code[indent..];
+        let len = line.iter().take_while(|ch| !ch.is_whitespace()).count();
+        let instruction = line[..len].iter().collect::<String>();
+        if !is_instruction(&instruction) {
+            shell::lex(line, indent, &mut words);
+            return words;
+        }
+
+        push_word(&mut words, indent, &line[..len], Scope::Keyword);
+
+        let args = &line[len..];
+        // Everything but the JSON (exec) form is shell-like enough to reuse the shell lexer for
+        // flags, strings and variables
+        let mut arg_words = Vec::new();
+        shell::lex(args, indent + len, &mut arg_words);
+        match instruction.to_ascii_uppercase().as_str() {
+            "RUN" | "CMD" | "ENTRYPOINT" | "SHELL" | "HEALTHCHECK" | "ONBUILD" => {}
+            _ => {
+                // Not shell commands, so don't highlight the first word as one
+                arg_words.retain(|word| word.scope != Scope::Function);
+            }
+        }
+        words.extend(arg_words);
+
+        // `FROM image AS name`
+        if instruction.eq_ignore_ascii_case("FROM") {
+            let mut col = indent + len;
+            for arg in args.split(|ch| ch.is_whitespace()) {
+                if arg.len() == 2 && arg.iter().collect::<String>().eq_ignore_ascii_case("as") {
+                    push_word(&mut words, col, arg, Scope::Keyword);
+                }
+                col += arg.len() + 1;
+            }
+            words.sort_by_key(|word| word.col);
+        }
+
+        words
+    }
+
+    fn split_words_in(&self, buf: &[Vec<char>], row: usize) -> Vec<Word> {
+        // Lines continued with a backslash are arguments of the instruction above them. Comments
+        // in between don't break the continuation.
+        let continued = buf[..row]
+            .iter()
+            .rev()
+            .find(|line| !is_comment(line))
+            .is_some_and(|line| is_continued(line));
+        if continued && !is_comment(&buf[row]) {
+            let mut words = Vec::new();
+            shell::lex(&buf[row], 0, &mut words);
+            return words;
+        }
+        self.split_words(&buf[row])
+    }
+
+    fn should_indent(&self, line: &[char]) -> bool {
+        is_continued(line) && !is_continued_from_above(line)
+    }
+
+    fn should_dedent(&self, _ch: char) -> bool {
+        false
+    }
+
+    fn line_comment(&self, _buf: &[Vec<char>]) -> Option<String> {
+        Some("#".into())
+    }
+}
+
+/// Only the first line of a continued instruction starts with the instruction itself, the
+/// following lines are already indented and shouldn't be indented any further.
+fn is_continued_from_above(line: &[char]) -> bool {
+    let indent = line.iter().take_while(|ch| ch.is_whitespace()).count();
+    let len = line[indent..]
+        .iter()
+        .take_while(|ch| !ch.is_whitespace())
+        .count();
+    !is_instruction(&line[indent..indent + len].iter().collect::<String>())
+}
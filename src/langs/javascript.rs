@@ -0,0 +1,459 @@
+use crate::*;
+
+fn is_quote(ch: char) -> bool {
+    ch == '"' || ch == '\''
+}
+
+fn is_ident(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_' || ch == '$'
+}
+
+fn is_number(word: &str) -> bool {
+    word.chars().next().filter(char::is_ascii_digit).is_some()
+}
+
+fn is_keyword(word: &str) -> bool {
+    matches!(
+        word,
+        "async"
+            | "await"
+            | "break"
+            | "case"
+            | "catch"
+            | "class"
+            | "const"
+            | "continue"
+            | "debugger"
+            | "default"
+            | "delete"
+            | "do"
+            | "else"
+            | "export"
+            | "extends"
+            | "false"
+            | "finally"
+            | "for"
+            | "from"
+            | "function"
+            | "get"
+            | "if"
+            | "import"
+            | "in"
+            | "instanceof"
+            | "let"
+            | "new"
+            | "null"
+            | "of"
+            | "return"
+            | "set"
+            | "static"
+            | "super"
+            | "switch"
+            | "this"
+            | "throw"
+            | "true"
+            | "try"
+            | "typeof"
+            | "undefined"
+            | "var"
+            | "void"
+            | "while"
+            | "with"
+            | "yield"
+    )
+}
+
+fn is_ts_keyword(word: &str) -> bool {
+    matches!(
+        word,
+        "abstract"
+            | "as"
+            | "asserts"
+            | "declare"
+            | "enum"
+            | "implements"
+            | "infer"
+            | "interface"
+            | "is"
+            | "keyof"
+            | "namespace"
+            | "override"
+            | "private"
+            | "protected"
+            | "public"
+            | "readonly"
+            | "satisfies"
+            | "type"
+    )
+}
+
+fn is_type(word: &str, typescript: bool) -> bool {
+    match word {
+        "any" | "bigint" | "boolean" | "never" | "number" | "object" | "string" | "symbol"
+        | "unknown" => typescript,
+        w => w.chars().next().filter(char::is_ascii_uppercase).is_some() && !w.contains('_'),
+    }
+}
+
+/// Keywords after which a `/` starts a regex literal instead of a division.
+fn is_expr_keyword(word: &str) -> bool {
+    matches!(
+        word,
+        "return"
+            | "typeof"
+            | "case"
+            | "in"
+            | "of"
+            | "new"
+            | "delete"
+            | "void"
+            | "throw"
+            | "yield"
+            | "await"
+            | "else"
+            | "do"
+    )
+}
+
+fn skip_spaces(code: &[char], mut i: usize) -> usize {
+    while i < code.len() && code[i].is_whitespace() {
+        i += 1;
+    }
+    i
+}
+
+/// Checks whether `code` (the text right after an identifier) assigns an arrow function to it,
+/// e.g. `= (a, b) =>`, `= async x =>` or `= (): void =>`.
+fn is_arrow_binding(code: &[char]) -> bool {
+    let mut i = skip_spaces(code, 0);
+    if code.get(i) != Some(&'=') || matches!(code.get(i + 1), Some(&'=') | Some(&'>')) {
+        return false;
+    }
+    i = skip_spaces(code, i + 1);
+    if code[i..].starts_with(&['a', 's', 'y', 'n', 'c'])
+        && !code.get(i + 5).is_some_and(|&ch| is_ident(ch))
+    {
+        i = skip_spaces(code, i + 5);
+    }
+
+    if code.get(i) == Some(&'(') {
+        let mut depth = 0;
+        while i < code.len() {
+            match code[i] {
+                '(' => depth += 1,
+                ')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        i += 1;
+        // Skip a return type annotation
+        if code.get(skip_spaces(code, i)) == Some(&':') {
+            while i < code.len() && !code[i..].starts_with(&['=', '>']) {
+                i += 1;
+            }
+        }
+    } else {
+        while i < code.len() && is_ident(code[i]) {
+            i += 1;
+        }
+    }
+
+    i = skip_spaces(code, i);
+    code[i.min(code.len())..].starts_with(&['=', '>'])
+}
+
+/// Lexes a template literal starting at `code[start]` (the opening backtick), including any
+/// `${}` interpolations. Returns the index right after the closing backtick.
+fn lex_template(
+    code: &[char],
+    start: usize,
+    offset: usize,
+    typescript: bool,
+    words: &mut Vec<Word>,
+) -> usize {
+    let mut word_start = start;
+    let mut i = start + 1;
+    while i < code.len() && code[i] != '`' {
+        if code[i] == '\\' {
+            i += 2;
+            continue;
+        }
+        if code[i] == '$' && code.get(i + 1) == Some(&'{') {
+            push_word(
+                words,
+                offset + word_start,
+                &code[word_start..i],
+                Scope::String,
+            );
+            push_word(words, offset + i, &code[i..i + 2], Scope::Special);
+            i += 2;
+
+            let inner_start = i;
+            let mut depth = 1;
+            while i < code.len() {
+                match code[i] {
+                    '{' => depth += 1,
+                    '}' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                    _ => {}
+                }
+                i += 1;
+            }
+            lex(
+                &code[inner_start..i],
+                offset + inner_start,
+                typescript,
+                words,
+            );
+
+            if i < code.len() {
+                push_word(words, offset + i, &code[i..i + 1], Scope::Special);
+                i += 1;
+            }
+            word_start = i;
+            continue;
+        }
+        i += 1;
+    }
+    let end = (i + 1).min(code.len());
+    push_word(
+        words,
+        offset + word_start,
+        &code[word_start..end],
+        Scope::String,
+    );
+    end
+}
+
+/// Returns the index right after the regex literal starting at `code[start]`, or `None` if the
+/// slash isn't terminated on this line (in which case it's probably just a division).
+fn regex_end(code: &[char], start: usize) -> Option<usize> {
+    let mut i = start + 1;
+    let mut in_class = false;
+    while i < code.len() {
+        match code[i] {
+            '\\' => i += 1,
+            '[' => in_class = true,
+            ']' => in_class = false,
+            '/' if !in_class => break,
+            _ => {}
+        }
+        i += 1;
+    }
+    if i >= code.len() || i == start + 1 {
+        return None;
+    }
+    i += 1;
+    while i < code.len() && code[i].is_ascii_alphabetic() {
+        i += 1;
+    }
+    Some(i)
+}
+
+fn lex(code: &[char], offset: usize, typescript: bool, words: &mut Vec<Word>) {
+    // Whether a `/` or `<` at this point would start an expression (regex literal or JSX tag)
+    let mut expr_start = true;
+
+    let mut i = 0;
+    while i < code.len() {
+        let ch = code[i];
+        let start = i;
+
+        if ch.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        if code[i..].starts_with(&['/', '/']) {
+            push_word(words, offset + i, &code[i..], Scope::Comment);
+            return;
+        }
+
+        if code[i..].starts_with(&['/', '*']) {
+            i += 2;
+            while i < code.len() && !code[i..].starts_with(&['*', '/']) {
+                i += 1;
+            }
+            i = (i + 2).min(code.len());
+            push_word(words, offset + start, &code[start..i], Scope::Comment);
+            continue;
+        }
+
+        if ch == '`' {
+            i = lex_template(code, i, offset, typescript, words);
+            expr_start = false;
+            continue;
+        }
+
+        if is_quote(ch) {
+            i += 1;
+            while i < code.len() && code[i] != ch {
+                if code[i] == '\\' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            i = (i + 1).min(code.len());
+            push_word(words, offset + start, &code[start..i], Scope::String);
+            expr_start = false;
+            continue;
+        }
+
+        if ch == '/' && expr_start {
+            if let Some(end) = regex_end(code, i) {
+                push_word(words, offset + start, &code[start..end], Scope::Special);
+                i = end;
+                expr_start = false;
+                continue;
+            }
+        }
+
+        if ch == '<' && expr_start {
+            let mut j = i + 1;
+            if code.get(j) == Some(&'/') {
+                j += 1;
+            }
+            if code.get(j).filter(|ch| ch.is_alphabetic()).is_some() {
+                while j < code.len() && (is_ident(code[j]) || code[j] == '.' || code[j] == '-') {
+                    j += 1;
+                }
+                push_word(words, offset + start, &code[start..j], Scope::Tag);
+                i = j;
+                expr_start = false;
+                continue;
+            }
+        }
+
+        if is_ident(ch) {
+            while i < code.len() && is_ident(code[i]) {
+                i += 1;
+            }
+            let word = code[start..i].iter().collect::<String>();
+
+            let scope = if is_number(&word) {
+                Scope::Number
+            } else if is_keyword(&word) || (typescript && is_ts_keyword(&word)) {
+                Scope::Keyword
+            } else if code.get(i) == Some(&'(') || is_arrow_binding(&code[i..]) {
+                Scope::Function
+            } else if is_type(&word, typescript) {
+                Scope::Type
+            } else {
+                Scope::Identifier
+            };
+            push_word(words, offset + start, &code[start..i], scope);
+
+            expr_start = is_expr_keyword(&word);
+            continue;
+        }
+
+        if code[i..].starts_with(&['=', '>']) {
+            push_word(words, offset + i, &code[i..i + 2], Scope::Keyword);
+            i += 2;
+            expr_start = true;
+            continue;
+        }
+
+        expr_start = !matches!(ch, ')' | ']' | '}' | '.');
+        i += 1;
+    }
+}
+
+fn split_words(code: &[char], typescript: bool) -> Vec<Word> {
+    let mut words = Vec::new();
+    lex(code, 0, typescript, &mut words);
+    words
+}
+
+fn trim_end(line: &[char]) -> &[char] {
+    let mut line = line;
+    while let Some((last, rest)) = line.split_last() {
+        if !last.is_whitespace() {
+            break;
+        }
+        line = rest;
+    }
+    line
+}
+
+/// Checks if the line ends with a JSX opening tag whose children will follow on the next lines.
+fn ends_with_open_tag(line: &[char]) -> bool {
+    if line.last() != Some(&'>') || line.ends_with(&['/', '>']) || line.ends_with(&['=', '>']) {
+        return false;
+    }
+    let Some(tag_start) = line.iter().rposition(|&ch| ch == '<') else {
+        return false;
+    };
+    match line.get(tag_start + 1) {
+        Some(&'>') => true, // fragment
+        Some(ch) => ch.is_alphabetic(),
+        None => false,
+    }
+}
+
+fn should_indent(line: &[char]) -> bool {
+    let line = trim_end(line);
+    matches!(line.last(), Some(&'{') | Some(&'(') | Some(&'[')) || ends_with_open_tag(line)
+}
+
+fn should_dedent(ch: char) -> bool {
+    matches!(ch, '}' | ')' | ']')
+}
+
+pub struct JavaScript;
+
+impl Language for JavaScript {
+    fn split_words(&self, code: &[char]) -> Vec<Word> {
+        split_words(code, false)
+    }
+
+    fn should_indent(&self, line: &[char]) -> bool {
+        should_indent(line)
+    }
+
+    fn should_dedent(&self, ch: char) -> bool {
+        should_dedent(ch)
+    }
+
+    fn line_comment(&self, _buf: &[Vec<char>]) -> Option<String> {
+        Some("//".into())
+    }
+
+    fn block_comment(&self) -> Option<(&'static str, &'static str)> {
+        Some(("/*", "*/"))
+    }
+}
+
+pub struct TypeScript;
+
+impl Language for TypeScript {
+    fn split_words(&self, code: &[char]) -> Vec<Word> {
+        split_words(code, true)
+    }
+
+    fn should_indent(&self, line: &[char]) -> bool {
+        should_indent(line)
+    }
+
+    fn should_dedent(&self, ch: char) -> bool {
+        should_dedent(ch)
+    }
+
+    fn line_comment(&self, _buf: &[Vec<char>]) -> Option<String> {
+        Some("//".into())
+    }
+
+    fn block_comment(&self) -> Option<(&'static str, &'static str)> {
+        Some(("/*", "*/"))
+    }
+}
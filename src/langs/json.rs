@@ -0,0 +1,214 @@
+use crate::*;
+
+fn is_key(code: &[char]) -> bool {
+    code.iter()
+        .find(|ch| !ch.is_whitespace())
+        .filter(|&&ch| ch == ':')
+        .is_some()
+}
+
+pub struct Json;
+
+impl Language for Json {
+    fn split_words(&self, code: &[char]) -> Vec<Word> {
+        let mut words = Vec::new();
+
+        let mut i = 0;
+        while i < code.len() {
+            let start = i;
+            let ch = code[i];
+            if ch == '"' {
+                i += 1;
+                while i < code.len() && code[i] != '"' {
+                    if code[i] == '\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                i = (i + 1).min(code.len());
+                let scope = if is_key(&code[i..]) {
+                    Scope::Property
+                } else {
+                    Scope::String
+                };
+                push_word(&mut words, start, &code[start..i], scope);
+            } else if ch == '-' || ch.is_ascii_digit() {
+                i += 1;
+                while i < code.len() && (code[i].is_ascii_alphanumeric() || "+-.".contains(code[i]))
+                {
+                    i += 1;
+                }
+                push_word(&mut words, start, &code[start..i], Scope::Number);
+            } else if ch.is_alphabetic() {
+                while i < code.len() && code[i].is_alphanumeric() {
+                    i += 1;
+                }
+                let word = code[start..i].iter().collect::<String>();
+                let scope = if matches!(word.as_str(), "true" | "false" | "null") {
+                    Scope::Constant
+                } else {
+                    Scope::Invalid
+                };
+                push_word(&mut words, start, &code[start..i], scope);
+            } else {
+                i += 1;
+            }
+        }
+
+        words
+    }
+
+    fn should_indent(&self, line: &[char]) -> bool {
+        matches!(line.last(), Some(&'{') | Some(&'['))
+    }
+
+    fn should_dedent(&self, ch: char) -> bool {
+        ch == '}' || ch == ']'
+    }
+
+    fn validate(&self, buf: &[Vec<char>]) -> Option<Result<(), SyntaxError>> {
+        let mut s = Scanner::new(buf);
+        Some(validate(&mut s))
+    }
+}
+
+fn skip_whitespace(s: &mut Scanner) {
+    while s.peek().filter(|ch| ch.is_whitespace()).is_some() {
+        s.bump();
+    }
+}
+
+fn validate(s: &mut Scanner) -> Result<(), SyntaxError> {
+    skip_whitespace(s);
+    value(s)?;
+    skip_whitespace(s);
+    if s.peek().is_some() {
+        return Err(s.error("unexpected data after the top-level value"));
+    }
+    Ok(())
+}
+
+fn value(s: &mut Scanner) -> Result<(), SyntaxError> {
+    match s.peek() {
+        Some('{') => object(s),
+        Some('[') => array(s),
+        Some('"') => string(s),
+        Some(ch) if ch == '-' || ch.is_ascii_digit() => number(s),
+        _ if s.eat_str("true") || s.eat_str("false") || s.eat_str("null") => Ok(()),
+        Some(_) => Err(s.error("expected a value")),
+        None => Err(s.error("unexpected end of file, expected a value")),
+    }
+}
+
+fn object(s: &mut Scanner) -> Result<(), SyntaxError> {
+    s.bump();
+    skip_whitespace(s);
+    if s.eat('}') {
+        return Ok(());
+    }
+    loop {
+        if s.peek() != Some('"') {
+            return Err(s.error("expected a string key"));
+        }
+        string(s)?;
+        skip_whitespace(s);
+        if !s.eat(':') {
+            return Err(s.error("expected ':'"));
+        }
+        skip_whitespace(s);
+        value(s)?;
+        skip_whitespace(s);
+        if s.eat('}') {
+            return Ok(());
+        }
+        if !s.eat(',') {
+            return Err(s.error("expected ',' or '}'"));
+        }
+        skip_whitespace(s);
+    }
+}
+
+fn array(s: &mut Scanner) -> Result<(), SyntaxError> {
+    s.bump();
+    skip_whitespace(s);
+    if s.eat(']') {
+        return Ok(());
+    }
+    loop {
+        value(s)?;
+        skip_whitespace(s);
+        if s.eat(']') {
+            return Ok(());
+        }
+        if !s.eat(',') {
+            return Err(s.error("expected ',' or ']'"));
+        }
+        skip_whitespace(s);
+    }
+}
+
+fn string(s: &mut Scanner) -> Result<(), SyntaxError> {
+    s.bump();
+    loop {
+        match s.peek() {
+            Some('"') => {
+                s.bump();
+                return Ok(());
+            }
+            Some('\\') => {
+                s.bump();
+                match s.peek() {
+                    Some('"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't') => {
+                        s.bump();
+                    }
+                    Some('u') => {
+                        s.bump();
+                        for _ in 0..4 {
+                            if s.peek().filter(char::is_ascii_hexdigit).is_none() {
+                                return Err(s.error("expected 4 hex digits in unicode escape"));
+                            }
+                            s.bump();
+                        }
+                    }
+                    _ => return Err(s.error("invalid escape sequence")),
+                }
+            }
+            Some('\n') | None => return Err(s.error("unterminated string")),
+            Some(ch) if ch.is_control() => {
+                return Err(s.error("control characters must be escaped in strings"))
+            }
+            Some(_) => {
+                s.bump();
+            }
+        }
+    }
+}
+
+fn digits(s: &mut Scanner) -> Result<(), SyntaxError> {
+    if s.peek().filter(char::is_ascii_digit).is_none() {
+        return Err(s.error("expected a digit"));
+    }
+    while s.peek().filter(char::is_ascii_digit).is_some() {
+        s.bump();
+    }
+    Ok(())
+}
+
+fn number(s: &mut Scanner) -> Result<(), SyntaxError> {
+    s.eat('-');
+    if s.eat('0') {
+        if s.peek().filter(char::is_ascii_digit).is_some() {
+            return Err(s.error("leading zeros are not allowed"));
+        }
+    } else {
+        digits(s)?;
+    }
+    if s.eat('.') {
+        digits(s)?;
+    }
+    if s.eat('e') || s.eat('E') {
+        let _ = s.eat('+') || s.eat('-');
+        digits(s)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,264 @@
+use super::shell;
+use crate::*;
+
+fn is_directive(word: &str) -> bool {
+    matches!(
+        word,
+        "include"
+            | "-include"
+            | "sinclude"
+            | "ifeq"
+            | "ifneq"
+            | "ifdef"
+            | "ifndef"
+            | "else"
+            | "endif"
+            | "define"
+            | "endef"
+            | "export"
+            | "unexport"
+            | "override"
+            | "private"
+            | "vpath"
+    )
+}
+
+/// Returns the index right after the variable reference starting at `code[start]` (a `$`).
+fn variable_end(code: &[char], start: usize) -> usize {
+    let close = match code.get(start + 1) {
+        Some('(') => ')',
+        Some('{') => '}',
+        Some(_) => return start + 2,
+        None => return start + 1,
+    };
+    let open = code[start + 1];
+    let mut depth = 0;
+    let mut i = start + 1;
+    while i < code.len() {
+        if code[i] == open {
+            depth += 1;
+        } else if code[i] == close {
+            depth -= 1;
+            if depth == 0 {
+                return i + 1;
+            }
+        }
+        i += 1;
+    }
+    code.len()
+}
+
+/// Lexes text that may contain variable references and comments
+fn lex_text(code: &[char], offset: usize, words: &mut Vec<Word>) {
+    let mut i = 0;
+    while i < code.len() {
+        match code[i] {
+            '#' => {
+                push_word(words, offset + i, &code[i..], Scope::Comment);
+                return;
+            }
+            '$' if code.get(i + 1) == Some(&'$') => i += 2,
+            '$' => {
+                let end = variable_end(code, i);
+                push_word(words, offset + i, &code[i..end], Scope::Variable);
+                i = end;
+            }
+            _ => i += 1,
+        }
+    }
+}
+
+/// Finds the `:` that separates targets from prerequisites in a rule, skipping variable references
+fn rule_separator(code: &[char]) -> Option<usize> {
+    let mut i = 0;
+    while i < code.len() {
+        match code[i] {
+            '$' => i = variable_end(code, i),
+            '#' | '=' => return None,
+            ':' if code.get(i + 1) == Some(&'=') => return None,
+            ':' => return Some(i),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// Finds the assignment operator (`=`, `:=`, `::=`, `?=`, `+=`, `!=`) in a variable assignment
+fn assignment_operator(code: &[char]) -> Option<(usize, usize)> {
+    let eq = code.iter().position(|&ch| ch == '=')?;
+    let mut start = eq;
+    while start > 0 && matches!(code[start - 1], ':' | '?' | '+' | '!') {
+        start -= 1;
+    }
+    let name = code[..start].iter().collect::<String>();
+    let name = name.trim();
+    let is_name =
+        !name.is_empty() && !name.contains(char::is_whitespace) && !name.contains(['#', ':']);
+    is_name.then_some((start, eq + 1))
+}
+
+/// Puts the `top` words over the `base` words, splitting the base words where they overlap.
+fn overlay(base: Vec<Word>, top: Vec<Word>) -> Vec<Word> {
+    let covered = |col: usize| {
+        top.iter()
+            .any(|word| (word.col..word.col + word.text.chars().count()).contains(&col))
+    };
+
+    let mut words = Vec::new();
+    for word in base {
+        let text = word.text.chars().collect::<Vec<_>>();
+        let mut seg_start = 0;
+        for i in 0..=text.len() {
+            if i == text.len() || covered(word.col + i) {
+                push_word(
+                    &mut words,
+                    word.col + seg_start,
+                    &text[seg_start..i],
+                    word.scope,
+                );
+                seg_start = i + 1;
+            }
+        }
+    }
+    words.extend(top);
+    words.sort_by_key(|word| word.col);
+    words
+}
+
+fn is_rule(line: &[char]) -> bool {
+    line.first() != Some(&'\t') && rule_separator(line).is_some()
+}
+
+/// Recipes must be indented with tabs, so space-indented lines inside a rule are errors
+fn is_space_indented_recipe(buf: &[Vec<char>], row: usize) -> bool {
+    let in_rule = buf[..row]
+        .iter()
+        .rev()
+        .find(|line| !line.iter().all(|ch| ch.is_whitespace()))
+        .is_some_and(|prev| prev.first() == Some(&'\t') || is_rule(prev));
+    buf[row].first() == Some(&' ') && in_rule
+}
+
+pub struct Makefile;
+
+impl Language for Makefile {
+    fn split_words(&self, code: &[char]) -> Vec<Word> {
+        let mut words = Vec::new();
+
+        if code.first() == Some(&'\t') {
+            // Recipe lines are shell code
+            let mut start = 1;
+            while matches!(code.get(start), Some('@' | '-' | '+')) {
+                start += 1;
+            }
+            push_word(&mut words, 1, &code[1..start], Scope::Special);
+            let mut vars = Vec::new();
+            let mut i = start;
+            while i < code.len() {
+                match code[i] {
+                    '$' if code.get(i + 1) == Some(&'$') => i += 2,
+                    '$' => {
+                        let end = variable_end(code, i);
+                        push_word(&mut vars, i, &code[i..end], Scope::Variable);
+                        i = end;
+                    }
+                    _ => i += 1,
+                }
+            }
+            let mut recipe = Vec::new();
+            shell::lex(&code[start..], start, &mut recipe);
+            // Make expands its own variables before the shell sees them
+            words.extend(overlay(recipe, vars));
+            return words;
+        }
+
+        let indent = code.iter().take_while(|ch| ch.is_whitespace()).count();
+        let line = &code[indent..];
+        let first_word_len = line.iter().take_while(|ch| !ch.is_whitespace()).count();
+        let first_word = line[..first_word_len].iter().collect::<String>();
+
+        if is_directive(&first_word) {
+            push_word(&mut words, indent, &line[..first_word_len], Scope::Keyword);
+            lex_text(&line[first_word_len..], indent + first_word_len, &mut words);
+        } else if let Some((op_start, op_end)) = assignment_operator(line) {
+            let name_len = line[..op_start]
+                .iter()
+                .take_while(|ch| !ch.is_whitespace())
+                .count();
+            push_word(&mut words, indent, &line[..name_len], Scope::Variable);
+            push_word(
+                &mut words,
+                indent + op_start,
+                &line[op_start..op_end],
+                Scope::Operator,
+            );
+            lex_text(&line[op_end..], indent + op_end, &mut words);
+        } else if let Some(sep) = rule_separator(line) {
+            let scope = if line.first() == Some(&'.') {
+                Scope::Keyword
+            } else {
+                Scope::Function
+            };
+            let mut targets = Vec::new();
+            lex_text(&line[..sep], indent, &mut targets);
+            // Color the targets, but keep the variable references in them highlighted
+            let mut col = 0;
+            for word in targets {
+                let start = word.col - indent;
+                push_word(&mut words, indent + col, &line[col..start], scope);
+                col = start + word.text.chars().count();
+                words.push(word);
+            }
+            push_word(&mut words, indent + col, &line[col..sep], scope);
+            lex_text(&line[sep..], indent + sep, &mut words);
+        } else {
+            lex_text(line, indent, &mut words);
+        }
+
+        words
+    }
+
+    fn split_words_in(&self, buf: &[Vec<char>], row: usize) -> Vec<Word> {
+        let line = &buf[row];
+        let indent = line.iter().take_while(|&&ch| ch == ' ').count();
+
+        if is_space_indented_recipe(buf, row) {
+            let mut words = vec![Word {
+                col: 0,
+                text: line[..indent].iter().collect(),
+                scope: Scope::Error,
+            }];
+            words.extend(
+                self.split_words(&line[indent..])
+                    .into_iter()
+                    .map(|mut word| {
+                        word.col += indent;
+                        word
+                    }),
+            );
+            return words;
+        }
+
+        self.split_words(line)
+    }
+
+    fn sign(&self, buf: &[Vec<char>], row: usize) -> Option<(char, Scope)> {
+        is_space_indented_recipe(buf, row).then_some(('!', Scope::Error))
+    }
+
+    fn should_indent(&self, line: &[char]) -> bool {
+        is_rule(line)
+    }
+
+    fn should_dedent(&self, _ch: char) -> bool {
+        false
+    }
+
+    fn line_comment(&self, _buf: &[Vec<char>]) -> Option<String> {
+        Some("#".into())
+    }
+
+    fn hard_tabs(&self) -> bool {
+        true
+    }
+}
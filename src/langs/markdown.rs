@@ -0,0 +1,326 @@
+use crate::*;
+
+/// If the line is a code fence (```` ``` ```` or `~~~`), returns the fence character, its length
+/// and the info string after it.
+fn fence_marker(line: &[char]) -> Option<(char, usize, &[char])> {
+    let indent = get_space_indent(line);
+    if indent > 3 {
+        return None;
+    }
+    let line = &line[indent..];
+    let ch = *line.first().filter(|&&ch| ch == '`' || ch == '~')?;
+    let len = line.iter().take_while(|&&c| c == ch).count();
+    if len < 3 {
+        return None;
+    }
+    Some((ch, len, &line[len..]))
+}
+
+/// An open code fence: its character, length and the language named in its info string
+type Fence = (char, usize, String);
+
+/// The code fence that is open after `line`, given the one that was open before it
+fn next_fence(open: Option<Fence>, line: &[char]) -> Option<Fence> {
+    let Some((ch, len, info)) = fence_marker(line) else {
+        return open;
+    };
+    match open {
+        None => {
+            let info = info.iter().collect::<String>();
+            let lang = info.split_whitespace().next().unwrap_or("").to_string();
+            Some((ch, len, lang))
+        }
+        Some((open_ch, open_len, _))
+            if ch == open_ch && len >= open_len && info.iter().all(|ch| ch.is_whitespace()) =>
+        {
+            None
+        }
+        open => open,
+    }
+}
+
+/// Finds the code fence that is still open right before `buf[row]`
+fn open_fence(buf: &[Vec<char>], row: usize) -> Option<Fence> {
+    buf[..row]
+        .iter()
+        .fold(None, |open, line| next_fence(open, line))
+}
+
+fn fence_lang(name: &str) -> &'static dyn Language {
+    // Fall back to treating the info string as a file extension, so that e.g. `rs` and `py` work
+    lang_from_name(name)
+        .or_else(|| lang_from_filename(&format!(".{name}")))
+        .or_else(|| lang_from_name(DEFAULT_LANG))
+        .expect("default language should exist")
+}
+
+/// Returns the length of the list marker (including the space after it) at the start of `line`.
+fn list_marker_len(line: &[char]) -> Option<usize> {
+    match line {
+        ['-' | '*' | '+', ' ', ..] => Some(2),
+        _ => {
+            let digits = line.iter().take_while(|ch| ch.is_ascii_digit()).count();
+            match line.get(digits..digits + 2) {
+                Some(['.' | ')', ' ']) if digits > 0 && digits <= 9 => Some(digits + 2),
+                _ => None,
+            }
+        }
+    }
+}
+
+fn is_thematic_break(line: &[char]) -> bool {
+    let chars: Vec<_> = line.iter().filter(|ch| !ch.is_whitespace()).collect();
+    chars.len() >= 3
+        && matches!(chars[0], '-' | '*' | '_')
+        && chars.iter().all(|&ch| ch == chars[0])
+}
+
+fn find_from(code: &[char], start: usize, needle: &[char]) -> Option<usize> {
+    (start..code.len()).find(|&i| code[i..].starts_with(needle))
+}
+
+/// Lexes inline markup: code spans, emphasis, links and images.
+fn lex_inline(code: &[char], offset: usize, words: &mut Vec<Word>) {
+    let mut i = 0;
+    while i < code.len() {
+        let ch = code[i];
+
+        if ch == '\\' {
+            i += 2;
+            continue;
+        }
+
+        if ch == '`' {
+            let ticks = code[i..].iter().take_while(|&&c| c == '`').count();
+            let delim = &code[i..i + ticks];
+            if let Some(end) = find_from(code, i + ticks, delim) {
+                let end = end + ticks;
+                push_word(words, offset + i, &code[i..end], Scope::String);
+                i = end;
+            } else {
+                i += ticks;
+            }
+            continue;
+        }
+
+        if ch == '*' || ch == '_' {
+            let strong = code.get(i + 1) == Some(&ch);
+            let delim = if strong {
+                &code[i..i + 2]
+            } else {
+                &code[i..i + 1]
+            };
+            let prev_is_word = i > 0 && code[i - 1].is_alphanumeric();
+            let next = code.get(i + delim.len());
+            let opens =
+                next.filter(|ch| !ch.is_whitespace()).is_some() && !(ch == '_' && prev_is_word);
+            if opens {
+                let close = find_from(code, i + delim.len() + 1, delim)
+                    .filter(|&end| !code[end - 1].is_whitespace());
+                if let Some(end) = close {
+                    let end = end + delim.len();
+                    let scope = if strong {
+                        Scope::Strong
+                    } else {
+                        Scope::Emphasis
+                    };
+                    push_word(words, offset + i, &code[i..end], scope);
+                    i = end;
+                    continue;
+                }
+            }
+            i += delim.len();
+            continue;
+        }
+
+        if ch == '[' || (ch == '!' && code.get(i + 1) == Some(&'[')) {
+            let text_start = if ch == '!' { i + 1 } else { i };
+            let link = find_from(code, text_start, &[']']).and_then(|text_end| {
+                let url_end = match code.get(text_end + 1) {
+                    Some('(') => find_from(code, text_end + 1, &[')'])?,
+                    Some('[') => find_from(code, text_end + 1, &[']'])?,
+                    _ => return None,
+                };
+                Some((text_end, url_end))
+            });
+            if let Some((text_end, url_end)) = link {
+                push_word(words, offset + i, &code[i..=text_end], Scope::Link);
+                push_word(
+                    words,
+                    offset + text_end + 1,
+                    &code[text_end + 1..=url_end],
+                    Scope::Delimiter,
+                );
+                i = url_end + 1;
+                continue;
+            }
+        }
+
+        if ch == '<' {
+            let autolink = find_from(code, i, &['>']).filter(|&end| {
+                let inner = code[i + 1..end].iter().collect::<String>();
+                inner.contains("://") || inner.contains('@')
+            });
+            if let Some(end) = autolink {
+                push_word(words, offset + i, &code[i..=end], Scope::Link);
+                i = end + 1;
+                continue;
+            }
+        }
+
+        i += 1;
+    }
+}
+
+fn lex_line(code: &[char], offset: usize, words: &mut Vec<Word>) {
+    let indent = get_space_indent(code);
+    let line = &code[indent..];
+    let offset = offset + indent;
+
+    if line.first() == Some(&'#') {
+        let level = line.iter().take_while(|&&ch| ch == '#').count();
+        if level <= 6 && line.get(level).filter(|ch| **ch != ' ').is_none() {
+            push_word(words, offset, line, Scope::Heading);
+            return;
+        }
+    }
+
+    if line.first() == Some(&'>') {
+        push_word(words, offset, &line[..1], Scope::Marker);
+        let rest = &line[1..];
+        let mut quote_words = Vec::new();
+        lex_line(rest, offset + 1, &mut quote_words);
+        if quote_words.is_empty() {
+            push_word(words, offset + 1, rest, Scope::Quote);
+        }
+        words.extend(quote_words);
+        return;
+    }
+
+    if is_thematic_break(line) {
+        push_word(words, offset, line, Scope::Delimiter);
+        return;
+    }
+
+    if let Some(marker_len) = list_marker_len(line) {
+        let mut marker_len = marker_len;
+        // Task list items: `- [ ] todo` / `- [x] done`
+        if matches!(
+            line.get(marker_len..marker_len + 3),
+            Some(['[', ' ' | 'x' | 'X', ']'])
+        ) {
+            marker_len += 3;
+        }
+        push_word(words, offset, &line[..marker_len], Scope::Marker);
+        lex_inline(&line[marker_len..], offset + marker_len, words);
+        return;
+    }
+
+    lex_inline(line, offset, words);
+}
+
+pub struct Markdown;
+
+impl Markdown {
+    fn split_row(&self, line: &[char], open: Option<&Fence>) -> Vec<Word> {
+        let fence_words = |line| {
+            vec![Word {
+                col: 0,
+                text: line,
+                scope: Scope::Delimiter,
+            }]
+        };
+
+        match open {
+            Some((open_ch, open_len, lang)) => match fence_marker(line) {
+                Some((ch, len, info))
+                    if ch == *open_ch
+                        && len >= *open_len
+                        && info.iter().all(|ch| ch.is_whitespace()) =>
+                {
+                    fence_words(line.iter().collect())
+                }
+                _ => fence_lang(lang).split_words(line),
+            },
+            None if fence_marker(line).is_some() => fence_words(line.iter().collect()),
+            None => self.split_words(line),
+        }
+    }
+}
+
+impl Language for Markdown {
+    fn split_words(&self, code: &[char]) -> Vec<Word> {
+        let mut words = Vec::new();
+        lex_line(code, 0, &mut words);
+        words
+    }
+
+    fn split_words_in(&self, buf: &[Vec<char>], row: usize) -> Vec<Word> {
+        self.split_row(&buf[row], open_fence(buf, row).as_ref())
+    }
+
+    fn split_rows_in(&self, buf: &[Vec<char>], rows: std::ops::Range<usize>) -> Vec<Vec<Word>> {
+        // Carry the open fence forward instead of looking for it again for every row
+        let mut open = open_fence(buf, rows.start);
+        rows.map(|row| {
+            let words = self.split_row(&buf[row], open.as_ref());
+            open = next_fence(open.take(), &buf[row]);
+            words
+        })
+        .collect()
+    }
+
+    fn should_indent(&self, _line: &[char]) -> bool {
+        false
+    }
+
+    fn should_dedent(&self, _ch: char) -> bool {
+        false
+    }
+
+    fn block_comment(&self) -> Option<(&'static str, &'static str)> {
+        Some(("<!--", "-->"))
+    }
+
+    fn line_continuation(&self, line: &[char]) -> Option<Vec<char>> {
+        let line = &line[get_space_indent(line)..];
+
+        if line.first() == Some(&'>') {
+            let rest = &line[1..];
+            if rest.iter().all(|ch| ch.is_whitespace()) {
+                return None;
+            }
+            let mut prefix = vec!['>', ' '];
+            if let Some(inner) = self.line_continuation(&rest[get_space_indent(rest)..]) {
+                prefix.extend(inner);
+            }
+            return Some(prefix);
+        }
+
+        let marker_len = list_marker_len(line)?;
+        let mut content = &line[marker_len..];
+        let is_task = matches!(content, ['[', ' ' | 'x' | 'X', ']', ..]);
+        if is_task {
+            content = &content[3..];
+        }
+        if content.iter().all(|ch| ch.is_whitespace()) {
+            // An empty item ends the list
+            return None;
+        }
+
+        let mut prefix: Vec<char> = match line[0] {
+            bullet @ ('-' | '*' | '+') => vec![bullet, ' '],
+            _ => {
+                let digits = &line[..marker_len - 2];
+                let n = digits.iter().collect::<String>().parse::<usize>().ok()?;
+                let mut prefix: Vec<char> = (n + 1).to_string().chars().collect();
+                prefix.extend_from_slice(&line[marker_len - 2..marker_len]);
+                prefix
+            }
+        };
+        if is_task {
+            prefix.extend_from_slice(&['[', ' ', ']', ' ']);
+        }
+        Some(prefix)
+    }
+}
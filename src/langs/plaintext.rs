@@ -1,5 +1,4 @@
-use crate::{Language, Word};
-use crossterm::style::{Attribute, Color};
+use crate::{Language, Scope, Word};
 
 pub struct Plaintext;
 impl Language for Plaintext {
@@ -7,8 +6,7 @@ impl Language for Plaintext {
         vec![Word {
             col: 0,
             text: code.iter().collect::<String>(),
-            color: Color::White,
-            attr: Attribute::Reset,
+            scope: Scope::Text,
         }]
     }
 
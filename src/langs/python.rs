@@ -1,5 +1,4 @@
 use crate::*;
-use crossterm::style::{Attribute, Color};
 
 fn is_quote(ch: char) -> bool {
     ch == '"' || ch == '\''
@@ -105,8 +104,7 @@ impl Language for Python {
                     words.push(Word {
                         col: pos - word.len(),
                         text: word,
-                        color: rgb_color(100, 100, 100),
-                        attr: Attribute::Italic,
+                        scope: Scope::Comment,
                     });
                 }
                 if !code.is_empty() {
@@ -116,8 +114,7 @@ impl Language for Python {
             }
 
             let mut word = String::new();
-            let color;
-            let attr;
+            let scope;
 
             if !code.is_empty() && is_quote(code[0]) {
                 let quote = code[0];
@@ -134,8 +131,7 @@ impl Language for Python {
                     pos += 1;
                     code = &code[1..];
                 }
-                color = Color::DarkGreen;
-                attr = Attribute::Reset;
+                scope = Scope::String;
             } else {
                 while !code.is_empty() && !is_ch_usable(code[0]) {
                     pos += 1;
@@ -147,27 +143,21 @@ impl Language for Python {
                     code = &code[1..];
                 }
                 if code.first().filter(|ch| ch == &&'(').is_some() {
-                    color = rgb_color(140, 201, 26);
-                    attr = Attribute::Bold;
+                    scope = Scope::Function;
                 } else if is_keyword(&word) {
-                    color = Color::Yellow;
-                    attr = Attribute::Bold;
+                    scope = Scope::Keyword;
                 } else if is_type(&word) {
-                    color = Color::Green;
-                    attr = Attribute::Reset;
+                    scope = Scope::Type;
                 } else if is_number(&word) {
-                    color = rgb_color(201, 146, 18);
-                    attr = Attribute::Reset;
+                    scope = Scope::Number;
                 } else {
-                    color = rgb_color(76, 111, 217);
-                    attr = Attribute::Reset;
+                    scope = Scope::Identifier;
                 }
             }
             words.push(Word {
                 col: pos - word.len(),
                 text: word,
-                color,
-                attr,
+                scope,
             });
         }
 
@@ -184,4 +174,8 @@ impl Language for Python {
     fn should_dedent(&self, _ch: char) -> bool {
         false
     }
+
+    fn line_comment(&self, _buf: &[Vec<char>]) -> Option<String> {
+        Some("#".into())
+    }
 }
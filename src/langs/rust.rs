@@ -1,5 +1,4 @@
 use crate::*;
-use crossterm::style::{Attribute, Color};
 
 fn is_quote(ch: char) -> bool {
     ch == '"' || ch == '\''
@@ -121,8 +120,7 @@ impl Language for Rust {
                     words.push(Word {
                         col: pos - word.len(),
                         text: word,
-                        color: rgb_color(100, 100, 100),
-                        attr: Attribute::Italic,
+                        scope: Scope::Comment,
                     });
                 }
                 if !code.is_empty() {
@@ -132,8 +130,7 @@ impl Language for Rust {
             }
 
             let mut word = String::new();
-            let color;
-            let attr;
+            let scope;
 
             if !code.is_empty() && is_quote(code[0]) {
                 let quote = code[0];
@@ -150,8 +147,7 @@ impl Language for Rust {
                     pos += 1;
                     code = &code[1..];
                 }
-                color = Color::DarkGreen;
-                attr = Attribute::Reset;
+                scope = Scope::String;
             } else {
                 while !code.is_empty() && !is_ch_usable(code[0]) {
                     pos += 1;
@@ -163,30 +159,23 @@ impl Language for Rust {
                     code = &code[1..];
                 }
                 if code.first().filter(|ch| ch == &&'!').is_some() {
-                    color = Color::DarkGreen;
-                    attr = Attribute::Reset;
+                    scope = Scope::Macro;
                 } else if code.first().filter(|ch| ch == &&'(').is_some() {
-                    color = rgb_color(140, 201, 26);
-                    attr = Attribute::Bold;
+                    scope = Scope::Function;
                 } else if is_keyword(&word) {
-                    color = Color::Yellow;
-                    attr = Attribute::Bold;
+                    scope = Scope::Keyword;
                 } else if is_type(&word) {
-                    color = Color::Green;
-                    attr = Attribute::Reset;
+                    scope = Scope::Type;
                 } else if is_number(&word) {
-                    color = rgb_color(201, 146, 18);
-                    attr = Attribute::Reset;
+                    scope = Scope::Number;
                 } else {
-                    color = rgb_color(76, 111, 217);
-                    attr = Attribute::Reset;
+                    scope = Scope::Identifier;
                 }
             }
             words.push(Word {
                 col: pos - word.len(),
                 text: word,
-                color,
-                attr,
+                scope,
             });
         }
 
@@ -200,4 +189,47 @@ impl Language for Rust {
     fn should_dedent(&self, ch: char) -> bool {
         ch == '}'
     }
+
+    fn line_comment(&self, _buf: &[Vec<char>]) -> Option<String> {
+        Some("//".into())
+    }
+
+    fn block_comment(&self) -> Option<(&'static str, &'static str)> {
+        Some(("/*", "*/"))
+    }
+
+    fn should_pair(&self, line: &[char], col: usize, open: char) -> bool {
+        open != '\'' || !is_lifetime_start(&line[..col])
+    }
+}
+
+/// Guesses whether a `'` typed after `before` starts a lifetime or a label rather than a
+/// character literal, as in `&'a`, `<'a>`, `T: 'static` or `break 'outer`
+fn is_lifetime_start(before: &[char]) -> bool {
+    let end = before.len()
+        - before
+            .iter()
+            .rev()
+            .take_while(|ch| ch.is_whitespace())
+            .count();
+    let trimmed = &before[..end];
+    let Some(&prev) = trimmed.last() else {
+        // Labels are usually the first thing on their line
+        return true;
+    };
+    if matches!(prev, '&' | '<' | ':' | '+') {
+        return true;
+    }
+    // `<'a, 'b>`, as opposed to `f('a', 'b')`
+    let unclosed_generics = trimmed.iter().filter(|&&ch| ch == '<').count()
+        > trimmed.iter().filter(|&&ch| ch == '>').count();
+    if prev == ',' && unclosed_generics {
+        return true;
+    }
+    let word_start = trimmed
+        .iter()
+        .rposition(|&ch| !is_ident(ch))
+        .map_or(0, |i| i + 1);
+    let word = trimmed[word_start..].iter().collect::<String>();
+    word == "break" || word == "continue"
 }
@@ -0,0 +1,228 @@
+use crate::*;
+
+fn is_ident(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+/// Characters that can appear in an unquoted word (command names, arguments, paths...)
+fn is_word_char(ch: char) -> bool {
+    !ch.is_whitespace() && !"'\"`$;&|<>(){}#".contains(ch)
+}
+
+fn is_keyword(word: &str) -> bool {
+    matches!(
+        word,
+        "if" | "then"
+            | "else"
+            | "elif"
+            | "fi"
+            | "for"
+            | "while"
+            | "until"
+            | "do"
+            | "done"
+            | "case"
+            | "esac"
+            | "in"
+            | "function"
+            | "select"
+            | "time"
+            | "return"
+            | "exit"
+            | "break"
+            | "continue"
+            | "local"
+            | "export"
+            | "readonly"
+            | "declare"
+            | "typeset"
+            | "unset"
+            | "shift"
+            | "source"
+            | "eval"
+            | "exec"
+            | "trap"
+            | "set"
+    )
+}
+
+/// Keywords after which a new command starts
+fn starts_command(word: &str) -> bool {
+    matches!(
+        word,
+        "if" | "then" | "else" | "elif" | "while" | "until" | "do" | "time" | "exec" | "!"
+    )
+}
+
+/// Lexes a variable or expansion starting at `code[start]` (a `$`). Returns the index right after
+/// it, or `None` if the `$` is just a literal character.
+fn variable_end(code: &[char], start: usize) -> Option<usize> {
+    let mut i = start + 1;
+    match code.get(i)? {
+        '{' => {
+            while i < code.len() && code[i] != '}' {
+                i += 1;
+            }
+            Some((i + 1).min(code.len()))
+        }
+        // Command substitution and arithmetic expansion: only highlight the opening part, the
+        // contents are lexed as regular code
+        '(' if code.get(i + 1) == Some(&'(') => Some(i + 2),
+        '(' => Some(i + 1),
+        '@' | '*' | '#' | '?' | '$' | '!' | '-' => Some(i + 1),
+        ch if ch.is_ascii_digit() => Some(i + 1),
+        ch if is_ident(*ch) => {
+            while i < code.len() && is_ident(code[i]) {
+                i += 1;
+            }
+            Some(i)
+        }
+        _ => None,
+    }
+}
+
+/// Lexes a line of shell code into `words`, starting at column `offset`
+pub fn lex(code: &[char], offset: usize, words: &mut Vec<Word>) {
+    let mut command_start = true;
+
+    let mut i = 0;
+    while i < code.len() {
+        let start = i;
+        let ch = code[i];
+
+        if ch.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        if ch == '#' && (i == 0 || code[i - 1].is_whitespace() || code[i - 1] == ';') {
+            push_word(words, offset + i, &code[i..], Scope::Comment);
+            return;
+        }
+
+        if ch == '\'' {
+            i += 1;
+            while i < code.len() && code[i] != '\'' {
+                i += 1;
+            }
+            i = (i + 1).min(code.len());
+            push_word(words, offset + start, &code[start..i], Scope::String);
+            command_start = false;
+            continue;
+        }
+
+        if ch == '"' {
+            // Double quoted strings can contain expansions
+            let mut word_start = i;
+            i += 1;
+            while i < code.len() && code[i] != '"' {
+                if code[i] == '\\' {
+                    i += 2;
+                    continue;
+                }
+                if code[i] == '$' {
+                    if let Some(end) = variable_end(code, i) {
+                        push_word(
+                            words,
+                            offset + word_start,
+                            &code[word_start..i],
+                            Scope::String,
+                        );
+                        push_word(words, offset + i, &code[i..end], Scope::Variable);
+                        i = end;
+                        word_start = i;
+                        continue;
+                    }
+                }
+                i += 1;
+            }
+            i = (i + 1).min(code.len());
+            push_word(
+                words,
+                offset + word_start,
+                &code[word_start..i],
+                Scope::String,
+            );
+            command_start = false;
+            continue;
+        }
+
+        if ch == '$' {
+            if let Some(end) = variable_end(code, i) {
+                push_word(words, offset + i, &code[i..end], Scope::Variable);
+                command_start = code[i + 1..end].ends_with(&['(']);
+                i = end;
+                continue;
+            }
+        }
+
+        if ch == '\\' {
+            // Escaped character or line continuation
+            i += 2;
+            continue;
+        }
+
+        if is_word_char(ch) {
+            while i < code.len() && is_word_char(code[i]) && code[i] != '=' {
+                i += 1;
+            }
+            let word = code[start..i].iter().collect::<String>();
+
+            if code.get(i) == Some(&'=') && command_start && word.chars().all(is_ident) {
+                // Variable assignment, which can be followed by the actual command
+                push_word(words, offset + start, &code[start..i], Scope::Variable);
+                i += 1;
+                while i < code.len() && is_word_char(code[i]) {
+                    i += 1;
+                }
+                continue;
+            }
+            while i < code.len() && is_word_char(code[i]) {
+                i += 1;
+            }
+
+            let next = code[i..].iter().find(|ch| !ch.is_whitespace());
+            if is_keyword(&word) && (command_start || matches!(word.as_str(), "in" | "do")) {
+                push_word(words, offset + start, &code[start..i], Scope::Keyword);
+                command_start = starts_command(&word);
+            } else if command_start || next == Some(&'(') {
+                push_word(words, offset + start, &code[start..i], Scope::Function);
+                command_start = false;
+            } else if word.chars().all(|ch| ch.is_ascii_digit()) {
+                push_word(words, offset + start, &code[start..i], Scope::Number);
+            }
+            continue;
+        }
+
+        // Operators
+        command_start = matches!(ch, ';' | '&' | '|' | '(' | '{' | '`' | '!');
+        i += 1;
+    }
+}
+
+pub struct Shell;
+
+impl Language for Shell {
+    fn split_words(&self, code: &[char]) -> Vec<Word> {
+        let mut words = Vec::new();
+        lex(code, 0, &mut words);
+        words
+    }
+
+    fn should_indent(&self, line: &[char]) -> bool {
+        let line = line.iter().collect::<String>();
+        let line = line.trim_end();
+        ["then", "do", "else", "{", "(", "in"].iter().any(|end| {
+            line.strip_suffix(end)
+                .is_some_and(|rest| rest.is_empty() || rest.ends_with([' ', ';', '\t']))
+        })
+    }
+
+    fn should_dedent(&self, ch: char) -> bool {
+        ch == '}'
+    }
+
+    fn line_comment(&self, _buf: &[Vec<char>]) -> Option<String> {
+        Some("#".into())
+    }
+}
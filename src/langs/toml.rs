@@ -0,0 +1,513 @@
+use crate::*;
+use std::collections::HashMap;
+
+fn is_bare_key_char(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || ch == '_' || ch == '-'
+}
+
+/// Returns the index right after the string starting at `code[start]`.
+fn string_end(code: &[char], start: usize) -> usize {
+    let quote = code[start];
+    let triple = code[start..].starts_with(&[quote, quote, quote]);
+    let mut i = start + if triple { 3 } else { 1 };
+    while i < code.len() {
+        if quote == '"' && code[i] == '\\' {
+            i += 2;
+            continue;
+        }
+        if triple && code[i..].starts_with(&[quote, quote, quote]) {
+            return i + 3;
+        }
+        if !triple && code[i] == quote {
+            return i + 1;
+        }
+        i += 1;
+    }
+    code.len()
+}
+
+pub struct Toml;
+
+impl Language for Toml {
+    fn split_words(&self, code: &[char]) -> Vec<Word> {
+        let mut words = Vec::new();
+
+        let indent = code.iter().take_while(|ch| ch.is_whitespace()).count();
+        if code.get(indent) == Some(&'[') {
+            let end = code
+                .iter()
+                .rposition(|&ch| ch == ']')
+                .map(|i| i + 1)
+                .unwrap_or(code.len());
+            if code[end..].iter().all(|ch| ch.is_whitespace()) || code[end..].contains(&'#') {
+                push_word(&mut words, indent, &code[indent..end], Scope::Heading);
+                if let Some(comment) = code[end..].iter().position(|&ch| ch == '#') {
+                    let comment = end + comment;
+                    push_word(&mut words, comment, &code[comment..], Scope::Comment);
+                }
+                return words;
+            }
+        }
+
+        // Everything before the first `=` on a line is a key, unless we're inside an inline table
+        let mut in_key = code.contains(&'=');
+        let mut i = 0;
+        while i < code.len() {
+            let start = i;
+            let ch = code[i];
+            if ch == '#' {
+                push_word(&mut words, i, &code[i..], Scope::Comment);
+                break;
+            } else if ch == '"' || ch == '\'' {
+                i = string_end(code, i);
+                let scope = if in_key {
+                    Scope::Property
+                } else {
+                    Scope::String
+                };
+                push_word(&mut words, start, &code[start..i], scope);
+            } else if ch == '=' {
+                in_key = false;
+                i += 1;
+            } else if ch == '{' || ch == ',' {
+                in_key = code[i..].contains(&'=');
+                i += 1;
+            } else if is_bare_key_char(ch) || ch == '+' {
+                while i < code.len() && (is_bare_key_char(code[i]) || "+.:".contains(code[i])) {
+                    i += 1;
+                }
+                let word = code[start..i].iter().collect::<String>();
+                let scope = if in_key {
+                    Scope::Property
+                } else if matches!(word.as_str(), "true" | "false") {
+                    Scope::Constant
+                } else if is_number(&word) || is_datetime(&word) {
+                    Scope::Number
+                } else {
+                    Scope::Invalid
+                };
+                push_word(&mut words, start, &code[start..i], scope);
+            } else {
+                i += 1;
+            }
+        }
+
+        words
+    }
+
+    fn should_indent(&self, line: &[char]) -> bool {
+        matches!(line.last(), Some(&'[') | Some(&'{'))
+    }
+
+    fn should_dedent(&self, ch: char) -> bool {
+        ch == ']' || ch == '}'
+    }
+
+    fn line_comment(&self, _buf: &[Vec<char>]) -> Option<String> {
+        Some("#".into())
+    }
+
+    fn validate(&self, buf: &[Vec<char>]) -> Option<Result<(), SyntaxError>> {
+        let mut s = Scanner::new(buf);
+        Some(validate(&mut s))
+    }
+}
+
+fn is_number(word: &str) -> bool {
+    let word = word.strip_prefix(['+', '-']).unwrap_or(word);
+    if matches!(word, "inf" | "nan") {
+        return true;
+    }
+    if word.is_empty() || word.starts_with('_') || word.ends_with('_') || word.contains("__") {
+        return false;
+    }
+    for (prefix, radix) in [("0x", 16), ("0o", 8), ("0b", 2)] {
+        if let Some(digits) = word.strip_prefix(prefix) {
+            return !digits.is_empty()
+                && !digits.starts_with('_')
+                && digits.chars().all(|ch| ch == '_' || ch.is_digit(radix));
+        }
+    }
+
+    let (mantissa, exponent) = match word.find(['e', 'E']) {
+        Some(i) => (&word[..i], Some(&word[i + 1..])),
+        None => (word, None),
+    };
+    let (int, frac) = match mantissa.split_once('.') {
+        Some((int, frac)) => (int, Some(frac)),
+        None => (mantissa, None),
+    };
+    let is_digits = |s: &str| {
+        !s.is_empty()
+            && !s.starts_with('_')
+            && !s.ends_with('_')
+            && s.chars().all(|ch| ch == '_' || ch.is_ascii_digit())
+    };
+
+    is_digits(int)
+        && !(int.len() > 1 && int.starts_with('0'))
+        && frac.is_none_or(is_digits)
+        && exponent.is_none_or(|exp| is_digits(exp.strip_prefix(['+', '-']).unwrap_or(exp)))
+}
+
+fn is_datetime(word: &str) -> bool {
+    let bytes = word.as_bytes();
+    let is_date = bytes.len() >= 10
+        && bytes[4] == b'-'
+        && bytes[7] == b'-'
+        && bytes[..10]
+            .iter()
+            .enumerate()
+            .all(|(i, b)| i == 4 || i == 7 || b.is_ascii_digit());
+    let is_time = bytes.len() >= 8
+        && bytes[2] == b':'
+        && bytes[5] == b':'
+        && bytes[..8]
+            .iter()
+            .enumerate()
+            .all(|(i, b)| i == 2 || i == 5 || b.is_ascii_digit());
+    let rest_ok = |rest: &str| {
+        rest.chars()
+            .all(|ch| ch.is_ascii_digit() || "TtZz:.+- ".contains(ch))
+    };
+    (is_date && rest_ok(&word[10..])) || (is_time && rest_ok(&word[8..]))
+}
+
+fn skip_spaces(s: &mut Scanner) {
+    while matches!(s.peek(), Some(' ' | '\t')) {
+        s.bump();
+    }
+}
+
+fn skip_comment(s: &mut Scanner) {
+    if s.peek() == Some('#') {
+        while s.peek().filter(|&ch| ch != '\n').is_some() {
+            s.bump();
+        }
+    }
+}
+
+/// Skips whitespace, newlines and comments, like the ones allowed inside arrays
+fn skip_blank(s: &mut Scanner) {
+    loop {
+        skip_spaces(s);
+        skip_comment(s);
+        if !s.eat('\n') {
+            break;
+        }
+    }
+}
+
+fn expect_line_end(s: &mut Scanner) -> Result<(), SyntaxError> {
+    skip_spaces(s);
+    skip_comment(s);
+    match s.peek() {
+        Some('\n') | None => Ok(()),
+        Some(_) => Err(s.error("expected a newline")),
+    }
+}
+
+/// What a key was defined as, to catch keys and tables that are defined twice
+enum Def {
+    Value,
+    Table(Table),
+    /// The last table of the array, since only it can still get new keys
+    ArrayOfTables(Table),
+}
+
+#[derive(Default)]
+struct Table {
+    keys: HashMap<String, Def>,
+    /// Whether the table has its own header, rather than only being implied by a longer one
+    defined: bool,
+}
+
+/// Follows `keys` down from `table`, adding the tables that don't exist yet. Fails with the
+/// index of the first key that isn't a table.
+fn descend<'a>(mut table: &'a mut Table, keys: &[String]) -> Result<&'a mut Table, usize> {
+    for (i, key) in keys.iter().enumerate() {
+        let def = table
+            .keys
+            .entry(key.clone())
+            .or_insert_with(|| Def::Table(Table::default()));
+        table = match def {
+            Def::Table(table) | Def::ArrayOfTables(table) => table,
+            Def::Value => return Err(i),
+        };
+    }
+    Ok(table)
+}
+
+/// Defines the table of a `[table]` or `[[array]]` header
+fn define_table(root: &mut Table, keys: &[String], array: bool) -> Result<(), String> {
+    let (last, parents) = keys.split_last().expect("a key has at least one part");
+    let parent =
+        descend(root, parents).map_err(|i| format!("{} is not a table", keys[..=i].join(".")))?;
+    let new = Table {
+        defined: true,
+        ..Table::default()
+    };
+    match parent.keys.get_mut(last) {
+        None if array => {
+            parent.keys.insert(last.clone(), Def::ArrayOfTables(new));
+        }
+        None => {
+            parent.keys.insert(last.clone(), Def::Table(new));
+        }
+        Some(Def::ArrayOfTables(table)) if array => *table = new,
+        Some(Def::Table(table)) if !array && !table.defined => table.defined = true,
+        Some(_) => return Err(format!("duplicate table [{}]", keys.join("."))),
+    }
+    Ok(())
+}
+
+fn validate(s: &mut Scanner) -> Result<(), SyntaxError> {
+    let mut root = Table::default();
+    let mut current = Vec::new();
+    loop {
+        skip_spaces(s);
+        match s.peek() {
+            None => return Ok(()),
+            Some('\n') => {
+                s.bump();
+                continue;
+            }
+            Some('#') => skip_comment(s),
+            Some('[') => {
+                s.bump();
+                let array = s.eat('[');
+                skip_spaces(s);
+                let (line, col) = (s.line, s.col);
+                let keys = key(s)?;
+                skip_spaces(s);
+                if !s.eat(']') || (array && !s.eat(']')) {
+                    return Err(s.error(if array {
+                        "expected ']]'"
+                    } else {
+                        "expected ']'"
+                    }));
+                }
+                define_table(&mut root, &keys, array).map_err(|msg| SyntaxError {
+                    line,
+                    col,
+                    msg,
+                })?;
+                current = keys;
+            }
+            Some(_) => {
+                let table = descend(&mut root, &current)
+                    .expect("the header of the current table made sure it's a table");
+                keyval(s, table)?;
+            }
+        }
+        expect_line_end(s)?;
+    }
+}
+
+/// Scans a key and returns its name. Escape sequences in quoted keys are kept as they are.
+fn simple_key(s: &mut Scanner) -> Result<String, SyntaxError> {
+    let (line, start) = (s.line, s.col);
+    match s.peek() {
+        Some('"') => basic_string(s)?,
+        Some('\'') => literal_string(s)?,
+        Some(ch) if is_bare_key_char(ch) => {
+            while s.peek().filter(|&ch| is_bare_key_char(ch)).is_some() {
+                s.bump();
+            }
+            return Ok(s.text_from(start));
+        }
+        _ => return Err(s.error("expected a key")),
+    }
+    if s.line != line {
+        return Err(SyntaxError {
+            line,
+            col: start,
+            msg: "keys can't span several lines".into(),
+        });
+    }
+    let text = s.text_from(start);
+    Ok(text[1..text.len() - 1].to_string())
+}
+
+fn key(s: &mut Scanner) -> Result<Vec<String>, SyntaxError> {
+    let mut keys = vec![simple_key(s)?];
+    loop {
+        skip_spaces(s);
+        if !s.eat('.') {
+            return Ok(keys);
+        }
+        skip_spaces(s);
+        keys.push(simple_key(s)?);
+    }
+}
+
+/// Scans a `key = value` pair and defines the key in `table`
+fn keyval(s: &mut Scanner, table: &mut Table) -> Result<(), SyntaxError> {
+    let (line, col) = (s.line, s.col);
+    let keys = key(s)?;
+    let err = |msg| SyntaxError { line, col, msg };
+    let (last, parents) = keys.split_last().expect("a key has at least one part");
+    let parent = descend(table, parents)
+        .map_err(|i| err(format!("{} is not a table", keys[..=i].join("."))))?;
+    if parent.keys.insert(last.clone(), Def::Value).is_some() {
+        return Err(err(format!("duplicate key {:?}", keys.join("."))));
+    }
+
+    skip_spaces(s);
+    if !s.eat('=') {
+        return Err(s.error("expected '='"));
+    }
+    skip_spaces(s);
+    value(s)
+}
+
+fn value(s: &mut Scanner) -> Result<(), SyntaxError> {
+    match s.peek() {
+        Some('"') => basic_string(s),
+        Some('\'') => literal_string(s),
+        Some('[') => array(s),
+        Some('{') => inline_table(s),
+        Some(ch) if is_bare_key_char(ch) || ch == '+' => {
+            let (line, col) = (s.line, s.col);
+            let mut word = String::new();
+            while let Some(ch) = s.peek() {
+                // Allow a single space between the date and time of a datetime
+                let is_datetime_space = ch == ' '
+                    && is_datetime(&word)
+                    && word.len() == 10
+                    && s.peek_nth(1).filter(char::is_ascii_digit).is_some();
+                if !(is_bare_key_char(ch) || "+.:".contains(ch) || is_datetime_space) {
+                    break;
+                }
+                word.push(ch);
+                s.bump();
+            }
+            if matches!(word.as_str(), "true" | "false") || is_number(&word) || is_datetime(&word) {
+                Ok(())
+            } else {
+                Err(SyntaxError {
+                    line,
+                    col,
+                    msg: format!("invalid value: {word:?}"),
+                })
+            }
+        }
+        Some('\n') | None => Err(s.error("expected a value")),
+        Some(_) => Err(s.error("invalid value")),
+    }
+}
+
+fn basic_string(s: &mut Scanner) -> Result<(), SyntaxError> {
+    let multiline = s.eat_str("\"\"\"");
+    if !multiline {
+        s.bump();
+    }
+    loop {
+        match s.peek() {
+            Some('"') if !multiline => {
+                s.bump();
+                return Ok(());
+            }
+            Some('"') if s.eat_str("\"\"\"") => {
+                // Up to two extra quotes are allowed right before the closing delimiter
+                let _ = s.eat('"') && s.eat('"');
+                return Ok(());
+            }
+            Some('\\') => {
+                s.bump();
+                match s.peek() {
+                    Some('b' | 't' | 'n' | 'f' | 'r' | 'e' | '"' | '\\') => {
+                        s.bump();
+                    }
+                    Some(u @ ('u' | 'U')) => {
+                        s.bump();
+                        for _ in 0..if u == 'u' { 4 } else { 8 } {
+                            if s.peek().filter(char::is_ascii_hexdigit).is_none() {
+                                return Err(s.error("invalid unicode escape"));
+                            }
+                            s.bump();
+                        }
+                    }
+                    Some(' ' | '\t' | '\n') if multiline => {
+                        // Line ending backslash
+                        while s.peek().filter(|ch| ch.is_whitespace()).is_some() {
+                            s.bump();
+                        }
+                    }
+                    _ => return Err(s.error("invalid escape sequence")),
+                }
+            }
+            Some('\n') if multiline => {
+                s.bump();
+            }
+            Some('\n') | None => return Err(s.error("unterminated string")),
+            Some(_) => {
+                s.bump();
+            }
+        }
+    }
+}
+
+fn literal_string(s: &mut Scanner) -> Result<(), SyntaxError> {
+    let multiline = s.eat_str("'''");
+    if !multiline {
+        s.bump();
+    }
+    loop {
+        match s.peek() {
+            Some('\'') if !multiline => {
+                s.bump();
+                return Ok(());
+            }
+            Some('\'') if s.eat_str("'''") => {
+                let _ = s.eat('\'') && s.eat('\'');
+                return Ok(());
+            }
+            Some('\n') if multiline => {
+                s.bump();
+            }
+            Some('\n') | None => return Err(s.error("unterminated string")),
+            Some(_) => {
+                s.bump();
+            }
+        }
+    }
+}
+
+fn array(s: &mut Scanner) -> Result<(), SyntaxError> {
+    s.bump();
+    loop {
+        skip_blank(s);
+        if s.eat(']') {
+            return Ok(());
+        }
+        value(s)?;
+        skip_blank(s);
+        if s.eat(']') {
+            return Ok(());
+        }
+        if !s.eat(',') {
+            return Err(s.error("expected ',' or ']'"));
+        }
+    }
+}
+
+fn inline_table(s: &mut Scanner) -> Result<(), SyntaxError> {
+    let mut table = Table::default();
+    s.bump();
+    skip_spaces(s);
+    if s.eat('}') {
+        return Ok(());
+    }
+    loop {
+        keyval(s, &mut table)?;
+        skip_spaces(s);
+        if s.eat('}') {
+            return Ok(());
+        }
+        if !s.eat(',') {
+            return Err(s.error("expected ',' or '}'"));
+        }
+        skip_spaces(s);
+    }
+}
@@ -0,0 +1,479 @@
+use crate::*;
+
+/// Checks if `code[i]` is a `:` that separates a key from its value
+fn is_key_separator(code: &[char], i: usize) -> bool {
+    code[i] == ':' && code.get(i + 1).filter(|ch| !ch.is_whitespace()).is_none()
+}
+
+/// Checks if `code[i]` is a `#` that starts a comment
+fn is_comment_start(code: &[char], i: usize) -> bool {
+    code[i] == '#' && (i == 0 || code[i - 1].is_whitespace())
+}
+
+fn quote_end(code: &[char], start: usize) -> usize {
+    let quote = code[start];
+    let mut i = start + 1;
+    while i < code.len() {
+        if quote == '"' && code[i] == '\\' {
+            i += 2;
+            continue;
+        }
+        if code[i] == quote {
+            // '' is an escaped quote inside single quoted strings
+            if quote == '\'' && code.get(i + 1) == Some(&'\'') {
+                i += 2;
+                continue;
+            }
+            return i + 1;
+        }
+        i += 1;
+    }
+    code.len()
+}
+
+fn is_number(word: &str) -> bool {
+    let word = word.strip_prefix(['+', '-']).unwrap_or(word);
+    word.parse::<f64>().is_ok()
+        || word.starts_with("0x")
+        || word.starts_with("0o")
+        || matches!(word, ".inf" | ".Inf" | ".INF" | ".nan" | ".NaN" | ".NAN")
+}
+
+fn is_constant(word: &str) -> bool {
+    matches!(
+        word,
+        "true"
+            | "True"
+            | "TRUE"
+            | "false"
+            | "False"
+            | "FALSE"
+            | "null"
+            | "Null"
+            | "NULL"
+            | "~"
+            | "yes"
+            | "Yes"
+            | "no"
+            | "No"
+            | "on"
+            | "On"
+            | "off"
+            | "Off"
+    )
+}
+
+pub struct Yaml;
+
+impl Language for Yaml {
+    fn split_words(&self, code: &[char]) -> Vec<Word> {
+        let mut words = Vec::new();
+
+        let indent = get_space_indent(code);
+        let line = code[indent..].iter().collect::<String>();
+        if line == "---" || line == "..." || line.starts_with('%') {
+            push_word(&mut words, 0, code, Scope::Keyword);
+            return words;
+        }
+
+        let mut i = indent;
+        // Sequence item markers
+        while code.get(i) == Some(&'-') && code.get(i + 1).filter(|ch| **ch != ' ').is_none() {
+            push_word(&mut words, i, &code[i..i + 1], Scope::Marker);
+            i += 1;
+            while code.get(i) == Some(&' ') {
+                i += 1;
+            }
+        }
+
+        while i < code.len() {
+            let start = i;
+            let ch = code[i];
+            if ch.is_whitespace() || "[]{},".contains(ch) {
+                i += 1;
+            } else if is_comment_start(code, i) {
+                push_word(&mut words, i, &code[i..], Scope::Comment);
+                break;
+            } else if ch == '"' || ch == '\'' {
+                i = quote_end(code, i);
+                let is_key = code[i..]
+                    .iter()
+                    .position(|ch| !ch.is_whitespace())
+                    .is_some_and(|j| is_key_separator(code, i + j));
+                let scope = if is_key {
+                    Scope::Property
+                } else {
+                    Scope::String
+                };
+                push_word(&mut words, start, &code[start..i], scope);
+            } else if ch == '&' || ch == '*' || ch == '!' {
+                while i < code.len() && !code[i].is_whitespace() && !"[]{},".contains(code[i]) {
+                    i += 1;
+                }
+                push_word(&mut words, start, &code[start..i], Scope::Special);
+            } else if ch == '|' || ch == '>' {
+                while i < code.len() && !code[i].is_whitespace() {
+                    i += 1;
+                }
+                push_word(&mut words, start, &code[start..i], Scope::Keyword);
+            } else {
+                // Plain scalar, which ends at a key separator, a comment or (in flow collections)
+                // a flow indicator
+                while i < code.len()
+                    && !is_key_separator(code, i)
+                    && !is_comment_start(code, i)
+                    && !"[]{},".contains(code[i])
+                {
+                    i += 1;
+                }
+                let mut end = i;
+                while end > start && code[end - 1].is_whitespace() {
+                    end -= 1;
+                }
+                let word = code[start..end].iter().collect::<String>();
+                let scope = if i < code.len() && is_key_separator(code, i) {
+                    i += 1;
+                    Scope::Property
+                } else if is_constant(&word) {
+                    Scope::Constant
+                } else if is_number(&word) {
+                    Scope::Number
+                } else {
+                    Scope::String
+                };
+                push_word(&mut words, start, &code[start..end], scope);
+            }
+        }
+
+        words
+    }
+
+    fn should_indent(&self, line: &[char]) -> bool {
+        let line = match line.iter().rposition(|ch| !ch.is_whitespace()) {
+            Some(end) => &line[..=end],
+            None => return false,
+        };
+        // Keys without an inline value and block scalar headers (`|`, `>-`, ...)
+        line.last() == Some(&':')
+            || line
+                .iter()
+                .rposition(|&ch| ch == '|' || ch == '>')
+                .is_some_and(|i| {
+                    line[i + 1..].iter().all(|ch| "+-0123456789".contains(*ch))
+                        && line[..i].ends_with(&[':', ' '])
+                })
+    }
+
+    fn should_dedent(&self, _ch: char) -> bool {
+        false
+    }
+
+    fn line_comment(&self, _buf: &[Vec<char>]) -> Option<String> {
+        Some("#".into())
+    }
+
+    fn validate(&self, buf: &[Vec<char>]) -> Option<Result<(), SyntaxError>> {
+        Some(Validator::default().validate(buf))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NodeKind {
+    Mapping,
+    Sequence,
+}
+
+/// A line-based structural checker. It doesn't understand all of YAML, but catches the common
+/// mistakes: tabs in indentation, bad indentation, mixing mappings and sequences and unterminated
+/// quotes or flow collections.
+#[derive(Default)]
+struct Validator {
+    /// Indentation and kind of the block collections we're currently inside of
+    stack: Vec<(usize, NodeKind)>,
+    /// Open flow collections (`[`, `{`) that continue on the next line
+    flow: Vec<char>,
+    /// Open quoted scalar that continues on the next line
+    quote: Option<char>,
+    /// Indentation of the line that started a block scalar (`|` or `>`)
+    block_scalar: Option<usize>,
+    /// Indentation of the last key or sequence item without an inline value, whose value
+    /// may follow on the next, more indented line
+    expect_child: Option<(usize, NodeKind)>,
+    /// Whether the previous line ended in a plain scalar that may continue on the next line
+    plain_continues: bool,
+}
+
+impl Validator {
+    fn validate(mut self, buf: &[Vec<char>]) -> Result<(), SyntaxError> {
+        for (line_idx, line) in buf.iter().enumerate() {
+            self.line(line, line_idx)?;
+        }
+
+        let line = buf.len().saturating_sub(1);
+        let col = buf.last().map_or(0, Vec::len);
+        let err = |msg: &str| SyntaxError {
+            line,
+            col,
+            msg: msg.into(),
+        };
+        if let Some(quote) = self.quote {
+            return Err(err(&format!("unterminated string, expected {quote}")));
+        }
+        if let Some(open) = self.flow.last() {
+            let close = if *open == '[' { ']' } else { '}' };
+            return Err(err(&format!("unexpected end of file, expected '{close}'")));
+        }
+        Ok(())
+    }
+
+    fn line(&mut self, line: &[char], line_idx: usize) -> Result<(), SyntaxError> {
+        let err = |col: usize, msg: &str| SyntaxError {
+            line: line_idx,
+            col,
+            msg: msg.into(),
+        };
+
+        let indent = get_space_indent(line);
+        let blank = line[indent..].iter().all(|ch| ch.is_whitespace())
+            || line[indent..].first() == Some(&'#');
+
+        if let Some(parent) = self.block_scalar {
+            if blank || indent > parent {
+                return Ok(());
+            }
+            self.block_scalar = None;
+        }
+
+        if self.quote.is_some() || !self.flow.is_empty() {
+            return self.scan(line, 0, line_idx).map(|_| ());
+        }
+
+        if blank {
+            return Ok(());
+        }
+
+        if line[indent] == '\t' {
+            return Err(err(indent, "tabs are not allowed for indentation"));
+        }
+
+        let text = line[indent..].iter().collect::<String>();
+        if text.starts_with("---") || text.starts_with("...") || text.starts_with('%') {
+            self.stack.clear();
+            self.expect_child = None;
+            self.plain_continues = false;
+            if text.starts_with("--- ") {
+                self.scan(line, indent + 4, line_idx)?;
+            }
+            return Ok(());
+        }
+
+        // Sequence items can start a mapping on the same line (`- key: value`), so a line can
+        // open several nested collections
+        let mut col = indent;
+        let mut nodes = Vec::new();
+        while line.get(col) == Some(&'-') && line.get(col + 1).filter(|ch| **ch != ' ').is_none() {
+            nodes.push((col, NodeKind::Sequence));
+            col += 1;
+            while line.get(col) == Some(&' ') {
+                col += 1;
+            }
+        }
+        let has_key = self.has_key(line, col);
+        if has_key || line.get(col) == Some(&'?') {
+            nodes.push((col, NodeKind::Mapping));
+        }
+
+        match nodes.first().copied() {
+            None => {
+                // A plain scalar, which is only allowed as the value of the previous line or as
+                // a continuation of a multi-line plain scalar
+                let is_root = self.stack.is_empty() && self.expect_child.is_none();
+                let nested = self.stack.last().is_none_or(|&(top, _)| indent > top);
+                let is_child = self.expect_child.is_some_and(|(parent, _)| indent > parent);
+                if !is_root && !(nested && (self.plain_continues || is_child)) {
+                    return Err(err(indent, "expected a mapping key or a sequence item"));
+                }
+            }
+            Some((node_indent, kind)) => {
+                if self.plain_continues && self.stack.last().is_some_and(|&(top, _)| indent > top) {
+                    return Err(err(col, "mapping values are not allowed here"));
+                }
+                self.enter(node_indent, kind)
+                    .map_err(|msg| err(node_indent, &msg))?;
+                for &(col, kind) in &nodes[1..] {
+                    self.stack.push((col, kind));
+                }
+            }
+        }
+
+        let value_start = if has_key {
+            let mut i = col;
+            while !is_key_separator(line, i) {
+                if (line[i] == '"' || line[i] == '\'') && i == col {
+                    i = quote_end(line, i) - 1;
+                }
+                i += 1;
+            }
+            i + 1
+        } else if line.get(col) == Some(&'?') {
+            col + 1
+        } else {
+            col
+        };
+
+        let has_value = self.scan(line, value_start, line_idx)?;
+        self.expect_child = if has_value {
+            None
+        } else {
+            nodes.last().copied()
+        };
+        Ok(())
+    }
+
+    /// Checks if the line has a `key:` starting at `col`
+    fn has_key(&self, line: &[char], col: usize) -> bool {
+        let mut i = col;
+        if matches!(line.get(i), Some('[' | '{')) {
+            return false;
+        }
+        while i < line.len() {
+            if is_comment_start(line, i) {
+                return false;
+            }
+            if (line[i] == '"' || line[i] == '\'') && i == col {
+                i = quote_end(line, i);
+                continue;
+            }
+            if is_key_separator(line, i) {
+                return true;
+            }
+            i += 1;
+        }
+        false
+    }
+
+    /// Updates the stack for a new block collection entry at `indent`.
+    fn enter(&mut self, indent: usize, kind: NodeKind) -> Result<(), String> {
+        if let Some((parent, parent_kind)) = self.expect_child.take() {
+            // The value of `key:` may be a sequence at the same indentation as the key
+            let indentless =
+                parent_kind == NodeKind::Mapping && kind == NodeKind::Sequence && indent == parent;
+            if indent > parent || indentless {
+                self.stack.push((indent, kind));
+                return Ok(());
+            }
+        }
+
+        while let Some(&(top, top_kind)) = self.stack.last() {
+            if top > indent {
+                self.stack.pop();
+                continue;
+            }
+            if top == indent && top_kind != kind {
+                // End of an indentless sequence
+                if top_kind == NodeKind::Sequence
+                    && self.stack.len() >= 2
+                    && self.stack[self.stack.len() - 2] == (indent, NodeKind::Mapping)
+                {
+                    self.stack.pop();
+                    continue;
+                }
+                return Err(match kind {
+                    NodeKind::Mapping => "expected a sequence item, found a mapping key".into(),
+                    NodeKind::Sequence => "expected a mapping key, found a sequence item".into(),
+                });
+            }
+            break;
+        }
+
+        match self.stack.last() {
+            Some(&(top, _)) if top == indent => Ok(()),
+            Some(_) => Err("bad indentation".into()),
+            None => {
+                self.stack.push((indent, kind));
+                Ok(())
+            }
+        }
+    }
+
+    /// Scans the value part of a line, tracking quotes and flow collections. Returns whether the
+    /// line had a value.
+    fn scan(&mut self, line: &[char], start: usize, line_idx: usize) -> Result<bool, SyntaxError> {
+        let err = |col: usize, msg: String| SyntaxError {
+            line: line_idx,
+            col,
+            msg,
+        };
+
+        let mut has_value = false;
+        self.plain_continues = false;
+
+        let mut i = start;
+        while i < line.len() {
+            let ch = line[i];
+
+            if let Some(quote) = self.quote {
+                if quote == '"' && ch == '\\' {
+                    i += 2;
+                    continue;
+                }
+                if ch == quote {
+                    if quote == '\'' && line.get(i + 1) == Some(&'\'') {
+                        i += 2;
+                        continue;
+                    }
+                    self.quote = None;
+                }
+                i += 1;
+                continue;
+            }
+
+            if ch.is_whitespace() {
+                i += 1;
+                continue;
+            }
+            if is_comment_start(line, i) {
+                break;
+            }
+            if (ch == '&' || ch == '!') && self.flow.is_empty() {
+                // Anchors and tags are properties of the value that follows, possibly on the
+                // next line
+                while i < line.len() && !line[i].is_whitespace() {
+                    i += 1;
+                }
+                continue;
+            }
+            has_value = true;
+
+            match ch {
+                '"' | '\'' => self.quote = Some(ch),
+                '[' | '{' => self.flow.push(ch),
+                ']' | '}' => {
+                    let open = if ch == ']' { '[' } else { '{' };
+                    if self.flow.pop() != Some(open) {
+                        return Err(err(i, format!("unexpected '{ch}'")));
+                    }
+                }
+                '|' | '>' if self.flow.is_empty() => {
+                    self.block_scalar = Some(get_space_indent(line));
+                    return Ok(true);
+                }
+                _ if self.flow.is_empty() => {
+                    // Plain scalar until the end of the line
+                    while i < line.len() && !is_comment_start(line, i) {
+                        if is_key_separator(line, i) {
+                            return Err(err(i, "mapping values are not allowed here".into()));
+                        }
+                        i += 1;
+                    }
+                    self.plain_continues = true;
+                    return Ok(true);
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+
+        Ok(has_value)
+    }
+}
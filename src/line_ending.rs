@@ -0,0 +1,72 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+    /// Classic Mac OS line endings
+    Cr,
+}
+
+impl LineEnding {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name.to_lowercase().as_str() {
+            "lf" => LineEnding::Lf,
+            "crlf" => LineEnding::Crlf,
+            "cr" => LineEnding::Cr,
+            _ => return None,
+        })
+    }
+
+    /// The name shown in the status line
+    pub fn name(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "LF",
+            LineEnding::Crlf => "CRLF",
+            LineEnding::Cr => "CR",
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+            LineEnding::Cr => "\r",
+        }
+    }
+
+    /// The line ending used most in `text`, or `None` if it doesn't have any line breaks
+    pub fn detect(text: &str) -> Option<Self> {
+        let (mut lf, mut crlf, mut cr) = (0, 0, 0);
+        let mut chars = text.chars().peekable();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '\r' if chars.next_if_eq(&'\n').is_some() => crlf += 1,
+                '\r' => cr += 1,
+                '\n' => lf += 1,
+                _ => {}
+            }
+        }
+        if lf + crlf + cr == 0 {
+            None
+        } else if lf >= crlf && lf >= cr {
+            Some(LineEnding::Lf)
+        } else if crlf >= cr {
+            Some(LineEnding::Crlf)
+        } else {
+            Some(LineEnding::Cr)
+        }
+    }
+
+    /// Splits `text` into lines ending with `self`. Lines of a CRLF file that end with a lone
+    /// `\n` are split too, so that mixed files don't end up with stray `\r`s.
+    pub fn split(self, text: &str) -> Vec<Vec<char>> {
+        match self {
+            LineEnding::Lf => text.split('\n').map(|row| row.chars().collect()).collect(),
+            LineEnding::Crlf => text
+                .split('\n')
+                .map(|row| row.strip_suffix('\r').unwrap_or(row).chars().collect())
+                .collect(),
+            LineEnding::Cr => text.split('\r').map(|row| row.chars().collect()).collect(),
+        }
+    }
+}
@@ -1,20 +1,32 @@
+mod brackets;
+mod editorconfig;
+mod encoding;
+mod file_stamp;
 mod langs;
+mod line_ending;
+mod options;
+mod pipe;
 mod plugin;
+mod save;
+mod swap;
+mod theme;
 
 use crossterm::{
     cursor::{self, MoveTo},
     event::*,
-    style::{self, Attribute, Color, Colors},
+    style::{self, Color, Colors},
     terminal::{self, Clear},
     ExecutableCommand, QueueableCommand,
 };
 use std::{
     cmp::Ordering,
     ffi::CString,
-    io::{Read, Write},
+    io::{IsTerminal, Write},
     num::NonZeroUsize,
+    ops::Range,
+    panic::AssertUnwindSafe,
     process::exit,
-    time::Duration,
+    time::{Duration, Instant},
 };
 #[cfg(debug_assertions)]
 use std::{
@@ -25,8 +37,14 @@ use std::{
 };
 
 use crossterm_display::*;
+use editorconfig::EditorConfig;
+use encoding::FileEncoding;
+use file_stamp::FileStamp;
 use langs::*;
+use line_ending::LineEnding;
+use options::*;
 use plugin::*;
+use theme::*;
 
 pub trait WriteChar {
     fn write_ch(&mut self, ch: char) -> Result<usize, std::io::Error>;
@@ -78,6 +96,9 @@ enum CursorState {
 enum PromptType {
     FileSave,
     QuitOnNoSave,
+    SaveLossy,
+    Recover,
+    ExternalChange,
     Command,
 }
 
@@ -87,7 +108,6 @@ struct Editor {
     cursor: Cursor,
     file_path: Option<String>,
     camera_topleft: Pos,
-    w: u16,
     h: u16,
     status: Vec<char>,
     status_prompt: String,
@@ -97,20 +117,58 @@ struct Editor {
     unsaved_changes: bool,
     clipboard: Option<Vec<Vec<char>>>,
     language: Box<dyn Language>,
+    language_name: String,
     plugins: Vec<Plugin>,
+    theme: Theme,
+    /// The options for the current buffer: the global ones with `.editorconfig` applied
+    options: Options,
+    /// The options set with the `set` command, which apply to every buffer
+    global_options: Options,
+    editorconfig: EditorConfig,
+    line_ending: LineEnding,
+    encoding: FileEncoding,
+    /// Whether the file had bytes that aren't valid in its encoding, which got replaced with
+    /// U+FFFD when loading it and would be lost when saving
+    lossy: bool,
+    /// Whether the file ends with a line break, which isn't a row of its own in `buf`
+    final_newline: bool,
+    /// Hash of what was last written to the swap file, if there is one
+    swap_hash: Option<u64>,
+    last_swap: Instant,
+    /// Contents of a swap file left behind for the current file, waiting for the user to decide
+    /// what to do with them
+    pending_recovery: Option<Vec<Vec<char>>>,
+    /// The file on disk as of the last load or save, to notice when another program changes it
+    file_stamp: Option<FileStamp>,
+    last_check: Instant,
+    /// Where the buffer goes when quitting, for using the editor in a pipeline
+    output: Option<std::fs::File>,
+    /// Refuse to change the buffer, for viewing files without editing them by accident
+    readonly: bool,
+    /// When the user last typed or clicked, to autosave once they stop
+    last_input: Instant,
+    /// The buffer and cursor position while the diff against a file changed on disk is shown
+    pending_external: Option<(Vec<Vec<char>>, Pos)>,
+    /// The last query searched for with Ctrl+F, whose matches are marked in the sign column
+    last_search: Option<Vec<char>>,
+    /// Words of the rows around the screen and the cursor, from the last time they were lexed
+    lexed: Option<LexedRows>,
+    /// Set when the buffer may have changed, to check if `lexed` is still up to date
+    lex_stale: bool,
 }
 
-const UI_WIDTH: u16 = 4;
 const UI_HEIGHT: u16 = 2;
 
-const BLACK: Color = rgb_color(0x18, 0x18, 0x18);
+const READONLY_ERROR: &str =
+    "ERROR: the buffer is read-only, use `save-as <path>` to save a copy you can edit";
+
 impl Editor {
     fn new<Lang: Language + 'static>(language: Lang) -> Result<Self, std::io::Error> {
         let buf = vec![Vec::new()];
 
         let display = TerminalDisplay::new()?;
 
-        let (w, h) = (display.w - UI_WIDTH, display.h - UI_HEIGHT);
+        let h = display.h - UI_HEIGHT;
         Ok(Self {
             display,
             buf,
@@ -121,7 +179,6 @@ impl Editor {
             },
             file_path: None,
             camera_topleft: (0, 0),
-            w,
             h,
             status: Vec::new(),
             status_prompt: String::new(),
@@ -131,7 +188,28 @@ impl Editor {
             unsaved_changes: true,
             clipboard: None,
             language: Box::new(language),
+            language_name: DEFAULT_LANG.into(),
             plugins: Vec::new(),
+            theme: Theme::load(DEFAULT_THEME).expect("built-in theme should be valid"),
+            options: Options::default(),
+            global_options: Options::default(),
+            editorconfig: EditorConfig::default(),
+            line_ending: LineEnding::default(),
+            encoding: FileEncoding::default(),
+            lossy: false,
+            final_newline: false,
+            swap_hash: None,
+            last_swap: Instant::now(),
+            pending_recovery: None,
+            file_stamp: None,
+            last_check: Instant::now(),
+            output: None,
+            readonly: false,
+            last_input: Instant::now(),
+            pending_external: None,
+            last_search: None,
+            lexed: None,
+            lex_stale: false,
         })
     }
 
@@ -147,17 +225,6 @@ impl Editor {
         Ok(())
     }
 
-    fn get_indent(mut row: &[char]) -> usize {
-        let mut res = 0;
-
-        while !row.is_empty() && row[0].is_ascii_whitespace() {
-            res += 1;
-            row = &row[1..];
-        }
-
-        res
-    }
-
     #[cfg(debug_assertions)]
     fn enable_logging(&mut self, port: u16) -> std::io::Result<()> {
         self.logger = Some(logger(port)?);
@@ -193,55 +260,815 @@ impl Editor {
     }
 
     fn load_file(&mut self, file_path: String) -> Result<(), std::io::Error> {
-        let f = std::fs::File::open(file_path.clone())?.bytes();
-
-        self.buf = Vec::new();
-        let mut row = Vec::new();
-        for ch in f {
-            let ch = ch?;
-            if ch == b'\n' {
-                self.buf
-                    .push(String::from_utf8_lossy(&row).chars().collect());
-                row = Vec::new();
-            } else {
-                row.push(ch);
-            }
-        }
-        self.buf
-            .push(String::from_utf8_lossy(&row).chars().collect());
+        self.load_file_as(file_path, None)
+    }
+
+    /// Loads a file, decoding it with `encoding` instead of detecting it
+    fn load_file_as(
+        &mut self,
+        file_path: String,
+        encoding: Option<FileEncoding>,
+    ) -> Result<(), std::io::Error> {
+        let bytes = std::fs::read(&file_path)?;
+        // The changes in the old buffer are gone now
+        self.remove_swap();
+        self.file_stamp = std::fs::metadata(&file_path)
+            .ok()
+            .map(|metadata| FileStamp::new(&metadata, &bytes));
+        self.pending_external = None;
+
+        self.editorconfig = EditorConfig::load(&file_path);
+        self.options = self.global_options.clone();
+        self.editorconfig.apply(&mut self.options);
+        self.set_contents(&bytes, encoding);
 
         self.file_path = Some(file_path.clone());
 
-        self.set_status(format!("Successfully loaded file {}", file_path));
+        if self.lossy {
+            self.set_status(format!(
+                "WARNING: {file_path} isn't valid {}, invalid bytes were replaced",
+                self.encoding.name()
+            ));
+        } else {
+            self.set_status(format!("Successfully loaded file {}", file_path));
+        }
         self.unsaved_changes = false;
+        let writable = save::is_writable(std::path::Path::new(&file_path));
+        self.readonly = self.options.readonly || !writable;
+        if !writable && !self.lossy {
+            self.set_status(format!(
+                "{file_path} isn't writable, opened it read-only (use `save-as <path>` to save a copy)"
+            ));
+        }
+
+        self.detect_language();
+        self.check_swap();
 
-        let default_lang = lang_from_name(DEFAULT_LANG).expect("default language should exist");
-        let lang = lang_from_filename(file_path.as_str()).unwrap_or(default_lang);
+        Ok(())
+    }
+
+    /// Loads text piped into the editor into a buffer without a file
+    fn load_stdin(&mut self, bytes: &[u8]) {
+        self.remove_swap();
+        self.file_stamp = None;
+        self.pending_external = None;
+        self.editorconfig = EditorConfig::default();
+        self.options = self.global_options.clone();
+        self.set_contents(bytes, None);
+        self.file_path = None;
+        self.readonly = self.options.readonly;
+        if self.lossy {
+            self.set_status(format!(
+                "WARNING: stdin isn't valid {}, invalid bytes were replaced",
+                self.encoding.name()
+            ));
+        }
+        self.unsaved_changes = false;
+        self.detect_language();
+    }
+
+    /// Decodes the contents of a file into the buffer, with `encoding` or the one it seems to use
+    fn set_contents(&mut self, bytes: &[u8], encoding: Option<FileEncoding>) {
+        self.lex_stale = true;
+        // A byte order mark is more reliable than what `.editorconfig` says
+        let detected = FileEncoding::detect(bytes);
+        self.encoding = encoding
+            .or(detected.bom.then_some(detected))
+            .or_else(|| {
+                self.editorconfig
+                    .get("charset")
+                    .and_then(FileEncoding::from_name)
+            })
+            .unwrap_or(detected);
+        let (text, lossy) = self.encoding.decode(bytes);
+        self.lossy = lossy;
+        // Files without line breaks get the line ending `.editorconfig` asks for
+        self.line_ending = LineEnding::detect(&text)
+            .or_else(|| {
+                self.editorconfig
+                    .get("end_of_line")
+                    .and_then(LineEnding::from_name)
+            })
+            .unwrap_or_default();
+        self.buf = self.line_ending.split(&text);
+        // The line break at the end of the file doesn't start another row
+        self.final_newline = self.buf.len() > 1 && self.buf.last().is_some_and(Vec::is_empty);
+        if self.final_newline {
+            self.buf.pop();
+        }
+    }
+
+    /// Splits the contents of a file into rows the way the current one was
+    fn decode_rows(&self, bytes: &[u8]) -> Vec<Vec<char>> {
+        let (text, _) = self.encoding.decode(bytes);
+        let mut rows = self.line_ending.split(&text);
+        if rows.len() > 1 && rows.last().is_some_and(Vec::is_empty) {
+            rows.pop();
+        }
+        rows
+    }
+
+    fn detect_language(&mut self) {
+        let file_path = self.file_path.as_deref().unwrap_or_default();
+        let (name, lang) = lang_from_file(file_path, &self.buf[0]);
         self.language = Box::new(lang);
+        self.language_name = name.into();
+    }
+
+    /// Writes the swap file of a modified buffer every `SWAP_INTERVAL`, so that the changes can
+    /// be recovered if the editor crashes
+    fn update_swap(&mut self) {
+        if !self.unsaved_changes
+            || self.showing_diff()
+            || self.last_swap.elapsed() < swap::SWAP_INTERVAL
+        {
+            return;
+        }
+        let Some(file_path) = &self.file_path else {
+            return;
+        };
+        self.last_swap = Instant::now();
+        let hash = swap::hash(&self.buf);
+        if self.swap_hash == Some(hash) {
+            return;
+        }
+        match swap::write(&swap::swap_path(file_path), &self.buf) {
+            Ok(()) => self.swap_hash = Some(hash),
+            Err(err) => self.log(format!("Could not write the swap file: {err}")),
+        }
+    }
+
+    fn remove_swap(&mut self) {
+        if let (Some(file_path), Some(_)) = (&self.file_path, self.swap_hash.take()) {
+            let _ = std::fs::remove_file(swap::swap_path(file_path));
+        }
+    }
+
+    /// Asks what to do with the swap file a crashed session left behind for the current file
+    fn check_swap(&mut self) {
+        let Some(file_path) = &self.file_path else {
+            return;
+        };
+        let swap_path = swap::swap_path(file_path);
+        let Ok(swap) = swap::read(&swap_path) else {
+            return;
+        };
+        if swap == self.buf {
+            let _ = std::fs::remove_file(swap_path);
+            return;
+        }
+        self.pending_recovery = Some(swap);
+        self.prompt_recovery();
+    }
 
+    fn prompt_recovery(&mut self) {
+        self.status.clear();
+        self.set_status_prompt(
+            "Found unsaved changes from a crashed session. Recover, diff or discard them? (r/d/n) "
+                .into(),
+            PromptType::Recover,
+        );
+    }
+
+    fn answer_recovery(&mut self, response: &str) {
+        let Some(swap) = self.pending_recovery.take() else {
+            return;
+        };
+        let file_path = self.file_path.clone().unwrap_or_default();
+        match response {
+            "r" | "R" => {
+                // The diff may be on screen instead of the file
+                if self.language_name == "diff" {
+                    let _ = self.reload_without_swap_check();
+                }
+                self.swap_hash = Some(swap::hash(&swap));
+                self.buf = swap;
+                self.unsaved_changes = true;
+                self.cursor.pos = (0, 0);
+                self.set_status(format!("Recovered unsaved changes to {file_path}"));
+            }
+            "d" | "D" => {
+                let diff = diff::unified(&file_path, "recovered changes", &self.buf, &swap);
+                self.buf = diff;
+                self.language = Box::new(diff::Diff);
+                self.language_name = "diff".into();
+                self.cursor.pos = (0, 0);
+                self.pending_recovery = Some(swap);
+                self.prompt_recovery();
+            }
+            "n" | "N" => {
+                if self.language_name == "diff" {
+                    let _ = self.reload_without_swap_check();
+                }
+                let _ = std::fs::remove_file(swap::swap_path(&file_path));
+                self.set_status("Discarded the unsaved changes".into());
+            }
+            _ => {
+                self.pending_recovery = Some(swap);
+                self.prompt_recovery();
+            }
+        }
+        self.cursor.selection_start = None;
+        self.camera_topleft = (0, 0);
+    }
+
+    /// Loads the current file again after showing the diff against its swap file
+    fn reload_without_swap_check(&mut self) -> Result<(), std::io::Error> {
+        let Some(file_path) = self.file_path.clone() else {
+            return Ok(());
+        };
+        let pending = self.pending_recovery.take();
+        let result = self.load_file_as(file_path, Some(self.encoding));
+        // Loading found the swap file again
+        self.cursor.state = CursorState::Default;
+        self.status_prompt.clear();
+        self.pending_recovery = pending;
+        result
+    }
+
+    /// Saves modified buffers somewhere they can be recovered from after a crash
+    fn dump_buffers(&mut self) {
+        if !self.unsaved_changes {
+            return;
+        }
+        let path = match &self.file_path {
+            Some(file_path) => swap::swap_path(file_path),
+            None => swap::recovery_path(),
+        };
+        match swap::write(&path, &self.buf) {
+            Ok(()) => eprintln!("Unsaved changes were saved to {}", path.display()),
+            Err(err) => eprintln!(
+                "Could not save unsaved changes to {}: {err}",
+                path.display()
+            ),
+        }
+    }
+
+    /// Reloads the file if another program changed it. Modified buffers aren't thrown away
+    /// without asking.
+    fn check_external_change(&mut self) {
+        if self.cursor.state != CursorState::Default
+            || self.last_check.elapsed() < file_stamp::CHECK_INTERVAL
+        {
+            return;
+        }
+        self.last_check = Instant::now();
+        let (Some(file_path), Some(stamp)) = (&self.file_path, &mut self.file_stamp) else {
+            return;
+        };
+        if !stamp.refresh(std::path::Path::new(file_path)) {
+            return;
+        }
+        if self.unsaved_changes {
+            self.prompt_external_change();
+        } else {
+            self.reload();
+        }
+    }
+
+    fn prompt_external_change(&mut self) {
+        self.status.clear();
+        self.set_status_prompt(
+            "The file was changed by another program. Reload, overwrite it, diff or keep editing? (r/o/d/n) ".into(),
+            PromptType::ExternalChange,
+        );
+    }
+
+    fn answer_external_change(&mut self, response: &str) -> Result<(), std::io::Error> {
+        match response {
+            "r" | "R" => self.reload(),
+            "o" | "O" => {
+                self.restore_pending_external();
+                // The stamp is already up to date, so this doesn't ask again
+                self.save_file()?;
+            }
+            "d" | "D" => {
+                self.restore_pending_external();
+                let file_path = self.file_path.clone().unwrap_or_default();
+                let on_disk = match std::fs::read(&file_path) {
+                    Ok(bytes) => self.decode_rows(&bytes),
+                    Err(err) => {
+                        self.set_status(format!("ERROR: could not read {file_path}: {err}"));
+                        return Ok(());
+                    }
+                };
+                let diff = diff::unified(&file_path, "buffer", &on_disk, &self.buf);
+                let buf = std::mem::replace(&mut self.buf, diff);
+                self.pending_external = Some((buf, self.cursor.pos));
+                self.language = Box::new(diff::Diff);
+                self.language_name = "diff".into();
+                self.cursor.pos = (0, 0);
+                self.cursor.selection_start = None;
+                self.camera_topleft = (0, 0);
+                self.prompt_external_change();
+            }
+            "n" | "N" => {
+                self.restore_pending_external();
+                self.set_status("Kept the buffer, saving will overwrite the file".into());
+            }
+            _ => self.prompt_external_change(),
+        }
         Ok(())
     }
 
-    fn save_file(&mut self) -> Result<(), std::io::Error> {
+    /// Whether a prompt is showing a diff in place of the buffer
+    fn showing_diff(&self) -> bool {
+        self.pending_external.is_some() || self.pending_recovery.is_some()
+    }
+
+    /// Asks again if a prompt is showing a diff in place of the buffer, since the diff mustn't
+    /// be saved or left on screen. Returns whether it did.
+    fn reprompt_diff(&mut self) -> bool {
+        if self.pending_external.is_some() {
+            self.prompt_external_change();
+            return true;
+        }
+        if self.pending_recovery.is_some() {
+            self.prompt_recovery();
+            return true;
+        }
+        false
+    }
+
+    /// Puts the buffer back after showing the diff against the file on disk
+    fn restore_pending_external(&mut self) {
+        if let Some((buf, pos)) = self.pending_external.take() {
+            self.buf = buf;
+            self.cursor.pos = pos;
+            self.camera_topleft = (0, 0);
+            self.detect_language();
+            self.update_camera();
+        }
+    }
+
+    /// Loads the current file again, keeping the cursor where it was
+    fn reload(&mut self) {
+        let Some(file_path) = self.file_path.clone() else {
+            return;
+        };
+        let pos = self
+            .pending_external
+            .take()
+            .map_or(self.cursor.pos, |(_, pos)| pos);
+        match self.load_file_as(file_path.clone(), Some(self.encoding)) {
+            Ok(()) => {
+                if self.cursor.state == CursorState::Default {
+                    self.set_status(format!(
+                        "Reloaded {file_path}, it was changed by another program"
+                    ));
+                }
+            }
+            Err(err) => {
+                self.set_status(format!("ERROR: could not reload {file_path}: {err}"));
+                return;
+            }
+        }
+        self.cursor.pos.1 = pos.1.min(self.buf.len() - 1);
+        self.cursor.pos.0 = pos.0;
+        self.cursor.selection_start = None;
+        self.move_cursor(0, 0);
+    }
+
+    /// Captures the mouse only while the `mouse` option is on, so that the terminal's own text
+    /// selection keeps working otherwise
+    fn update_mouse_capture(&mut self) -> Result<(), std::io::Error> {
+        if self.options.mouse {
+            self.display.stdout.queue(EnableMouseCapture)?;
+        } else {
+            self.display.stdout.queue(DisableMouseCapture)?;
+        }
+        Ok(())
+    }
+
+    /// Saves the file once the user hasn't done anything for as long as the `autosave` option
+    /// says
+    fn autosave_when_idle(&mut self) {
+        let Some(seconds) = self.options.autosave else {
+            return;
+        };
+        if self.last_input.elapsed() >= Duration::from_secs(seconds as u64) {
+            self.autosave();
+        }
+    }
+
+    fn autosave(&mut self) {
+        // Anything that would need a prompt waits for the user to save by hand
+        if self.options.autosave.is_none()
+            || self.readonly
+            || !self.unsaved_changes
+            || self.showing_diff()
+            || self.file_path.is_none()
+            || self.lossy
+            || self.cursor.state != CursorState::Default
+        {
+            return;
+        }
+        // Don't try again right away if it fails
+        self.last_input = Instant::now();
+        if let Err(err) = self.save_file() {
+            self.set_status(format!("ERROR: could not autosave: {err}"));
+        }
+    }
+
+    fn quit(&mut self) -> ! {
+        // Write the buffer to stdout, not the diff
+        self.restore_pending_external();
+        if self.pending_recovery.is_some() && self.language_name == "diff" {
+            let _ = self.reload_without_swap_check();
+        }
+        self.remove_swap();
+        if let Some(mut output) = self.output.take() {
+            restore_terminal();
+            let text = self.contents();
+            let bytes = self
+                .encoding
+                .encode(&text)
+                .unwrap_or_else(|| text.into_bytes());
+            if let Err(err) = output.write_all(&bytes) {
+                eprintln!("Could not write the buffer to stdout: {err}");
+                exit(1);
+            }
+            // Clearing the terminal again would hide the buffer if stdout is the terminal
+            exit(0);
+        }
+        quit()
+    }
+
+    /// Saves the buffer, returning whether it was actually written. It isn't if the user still
+    /// has to answer a prompt or the text can't be encoded.
+    fn save_file(&mut self) -> Result<bool, std::io::Error> {
+        if self.reprompt_diff() {
+            return Ok(false);
+        }
+        if self.readonly {
+            self.set_status(READONLY_ERROR.into());
+            return Ok(false);
+        }
         if self.file_path.is_none() {
             self.set_status_prompt("File path: ".into(), PromptType::FileSave);
-            return Ok(());
+            return Ok(false);
+        }
+        if self.lossy {
+            self.set_status_prompt(
+                format!(
+                    "Invalid {} was replaced when loading, saving will lose it. Save anyway? (y/n) ",
+                    self.encoding.name()
+                ),
+                PromptType::SaveLossy,
+            );
+            return Ok(false);
+        }
+        // Files are written back exactly as they were loaded unless they were edited
+        if self.unsaved_changes {
+            self.lex_stale = true;
+            if self.options.trim_trailing_whitespace {
+                for row in &mut self.buf {
+                    let len =
+                        row.len() - row.iter().rev().take_while(|ch| ch.is_whitespace()).count();
+                    row.truncate(len);
+                }
+                self.move_cursor(0, 0);
+            }
+            if let Some(insert) = self.options.insert_final_newline {
+                self.final_newline = insert;
+            }
         }
-        let mut f = std::fs::File::create(self.file_path.clone().unwrap())?;
 
-        for row in &self.buf {
-            f.write_all(row.iter().collect::<String>().as_bytes())?;
-            // TODO: support different line endings
-            f.write_ch('\n')?;
+        let Some(bytes) = self.encoding.encode(&self.contents()) else {
+            self.set_status(format!(
+                "ERROR: the buffer has characters that {} can't represent, change it with the `encoding` command",
+                self.encoding.name()
+            ));
+            return Ok(false);
+        };
+        let file_path = self.file_path.clone().unwrap();
+        let path = std::path::Path::new(&file_path);
+        // Don't silently throw away what another program wrote
+        if self
+            .file_stamp
+            .as_mut()
+            .is_some_and(|stamp| stamp.refresh(path))
+        {
+            self.prompt_external_change();
+            return Ok(false);
         }
+        save::write_file(path, &bytes, self.options.backup)?;
+        self.file_stamp = std::fs::metadata(path)
+            .ok()
+            .map(|metadata| FileStamp::new(&metadata, &bytes));
 
         self.set_status(format!(
             "Successfully saved file to {}",
             self.file_path.clone().unwrap()
         ));
         self.unsaved_changes = false;
+        self.remove_swap();
 
-        Ok(())
+        Ok(true)
+    }
+
+    /// The text of the buffer as it's written to the file
+    fn contents(&self) -> String {
+        let line_ending = self.line_ending.as_str();
+        let mut text = String::new();
+        for (i, row) in self.buf.iter().enumerate() {
+            text.extend(row);
+            if i + 1 < self.buf.len() || self.final_newline {
+                text.push_str(line_ending);
+            }
+        }
+        text
+    }
+
+    /// Saves the buffer to another file, which is then the one being edited. This is also the way
+    /// out of a read-only buffer.
+    fn save_as(&mut self, file_path: String) -> Result<bool, std::io::Error> {
+        self.remove_swap();
+        let old_path = self.file_path.replace(file_path);
+        let old_stamp = self.file_stamp.take();
+        let readonly = std::mem::replace(&mut self.readonly, false);
+        self.save_file().inspect_err(|_| {
+            self.file_path = old_path;
+            self.file_stamp = old_stamp;
+            self.readonly = readonly;
+        })
+    }
+
+    /// Applies the diff hunk under the cursor to the file it targets, or undoes it if `reverse`
+    fn apply_hunk(&mut self, reverse: bool) -> Result<String, String> {
+        let hunk = diff::hunk_at(&self.buf, self.cursor.pos.1)?;
+        let path = hunk.path.ok_or("the hunk doesn't name a target file")?;
+        // Paths in the diff are relative to where it was made, which is usually either the
+        // working directory or the directory of the diff itself
+        let path = std::path::Path::new(&path);
+        let path = self
+            .file_path
+            .as_ref()
+            .and_then(|file_path| std::path::Path::new(file_path).parent())
+            .map(|dir| dir.join(path))
+            .filter(|candidate| !path.exists() && candidate.exists())
+            .unwrap_or_else(|| path.to_path_buf());
+
+        // The buffer would go out of sync with the file, and unsaved changes would be lost
+        let is_open = self
+            .file_path
+            .as_ref()
+            .and_then(|file_path| std::fs::canonicalize(file_path).ok())
+            .is_some_and(|open| std::fs::canonicalize(&path).is_ok_and(|target| target == open));
+        if is_open && self.unsaved_changes {
+            return Err(format!(
+                "{} has unsaved changes, save it first",
+                path.display()
+            ));
+        }
+
+        let bytes = std::fs::read(&path)
+            .map_err(|err| format!("could not read {}: {err}", path.display()))?;
+        // Write the file back the way it was, only changing the lines of the hunk
+        let encoding = FileEncoding::detect(&bytes);
+        let (text, _) = encoding.decode(&bytes);
+        let line_ending = LineEnding::detect(&text).unwrap_or(self.line_ending);
+        let final_newline = text.is_empty() || text.ends_with(['\n', '\r']);
+        let mut lines = Vec::new();
+        if !text.is_empty() {
+            lines = line_ending
+                .split(&text)
+                .into_iter()
+                .map(String::from_iter)
+                .collect();
+            if final_newline {
+                lines.pop();
+            }
+        }
+        let (from, to, hint) = if reverse {
+            (&hunk.new_lines, &hunk.old_lines, hunk.new_start)
+        } else {
+            (&hunk.old_lines, &hunk.new_lines, hunk.old_start)
+        };
+        diff::splice(&mut lines, from, to, hint)?;
+
+        let mut contents = lines.join(line_ending.as_str());
+        if final_newline && !lines.is_empty() {
+            contents.push_str(line_ending.as_str());
+        }
+        let bytes = encoding.encode(&contents).ok_or_else(|| {
+            format!(
+                "the hunk has characters that {} can't represent",
+                encoding.name()
+            )
+        })?;
+        save::write_file(&path, &bytes, self.options.backup)
+            .map_err(|err| format!("could not write {}: {err}", path.display()))?;
+        if is_open {
+            self.reload();
+        }
+
+        let verb = if reverse { "Reverted" } else { "Applied" };
+        Ok(format!("{verb} hunk in {}", path.display()))
+    }
+
+    /// Moves the cursor to the next (or previous) hunk header in a diff
+    fn jump_to_hunk(&mut self, forward: bool) -> Option<String> {
+        let row = self.cursor.pos.1;
+        let mut headers = diff::hunk_headers(&self.buf);
+        let target = if forward {
+            headers.find(|&header| header > row)
+        } else {
+            headers.rfind(|&header| header < row)
+        };
+        drop(headers);
+        let Some(target) = target else {
+            return Some(format!(
+                "No {} hunk",
+                if forward { "next" } else { "previous" }
+            ));
+        };
+        self.cursor.selection_start = None;
+        self.cursor.pos = (0, target);
+        self.move_cursor(0, 0);
+        None
+    }
+
+    /// The first and last row of the selection, or the cursor's row if nothing is selected
+    fn selected_rows(&self) -> (usize, usize) {
+        match self.cursor.selection_start {
+            Some(sel) => {
+                let ((_, sy), (_, cy)) = Cursor::minmax_pos(sel, self.cursor.pos);
+                (sy, cy)
+            }
+            None => (self.cursor.pos.1, self.cursor.pos.1),
+        }
+    }
+
+    /// Cycles the action (pick, squash, fixup...) of the selected lines in a rebase todo file
+    fn cycle_rebase_action(&mut self, forward: bool) -> Option<String> {
+        let (start, end) = self.selected_rows();
+
+        let mut changed = false;
+        for row in start..=end {
+            if let Some(line) = commit::cycle_action(&self.buf[row], forward) {
+                self.buf[row] = line;
+                changed = true;
+            }
+        }
+        if !changed {
+            return Some("ERROR: no rebase todo line to change".into());
+        }
+
+        self.unsaved_changes = true;
+        self.move_cursor(0, 0);
+        None
+    }
+
+    /// Inserts `text` at `(col, row)`, moving the cursor and the selection along with the text
+    fn insert_at(&mut self, (col, row): Pos, text: &[char]) {
+        self.buf[row].splice(col..col, text.iter().copied());
+        self.shift_cursor((col, row), text.len() as isize);
+    }
+
+    /// Removes `len` characters at `(col, row)`, moving the cursor and the selection along with
+    /// the text
+    fn remove_at(&mut self, (col, row): Pos, len: usize) {
+        self.buf[row].drain(col..col + len);
+        self.shift_cursor((col, row), -(len as isize));
+    }
+
+    fn shift_cursor(&mut self, (col, row): Pos, delta: isize) {
+        let positions = [
+            Some(&mut self.cursor.pos),
+            self.cursor.selection_start.as_mut(),
+        ];
+        for (x, y) in positions.into_iter().flatten() {
+            if *y == row && *x >= col {
+                *x = (*x as isize + delta).max(col as isize) as usize;
+            }
+        }
+    }
+
+    /// The characters inserted for one level of indentation
+    fn indent_unit(&self) -> Vec<char> {
+        if self
+            .options
+            .hard_tabs
+            .unwrap_or_else(|| self.language.hard_tabs())
+        {
+            vec!['\t']
+        } else {
+            vec![' '; self.options.indent_width]
+        }
+    }
+
+    /// Indents every selected line (or the current one) by one level, leaving blank lines alone
+    fn indent_selection(&mut self) {
+        let (start, end) = self.selected_rows();
+        let unit = self.indent_unit();
+        for row in start..=end {
+            if !self.buf[row].is_empty() {
+                self.insert_at((0, row), &unit);
+            }
+        }
+        self.move_cursor(0, 0);
+    }
+
+    /// Removes one level of indentation from every selected line (or the current one)
+    fn dedent_selection(&mut self) {
+        let (start, end) = self.selected_rows();
+        let mut changed = false;
+        for row in start..=end {
+            let line = &self.buf[row];
+            let len = if line.first() == Some(&'\t') {
+                1
+            } else {
+                line.iter()
+                    .take(self.options.indent_width)
+                    .take_while(|&&ch| ch == ' ')
+                    .count()
+            };
+            if len > 0 {
+                self.remove_at((0, row), len);
+                changed = true;
+            }
+        }
+        if changed {
+            self.unsaved_changes = true;
+        }
+        self.move_cursor(0, 0);
+    }
+
+    /// Comments out the selected lines (or the current one), or uncomments them if they're all
+    /// commented already. Line comments are inserted at the smallest indentation of the lines so
+    /// that they stay aligned; languages with only block comments get the lines wrapped in one.
+    fn toggle_comment(&mut self) -> Option<String> {
+        let (start, end) = self.selected_rows();
+        let rows = (start..=end)
+            .filter(|&row| self.buf[row].iter().any(|ch| !ch.is_whitespace()))
+            .collect::<Vec<_>>();
+        let (Some(&first), Some(&last)) = (rows.first(), rows.last()) else {
+            return None;
+        };
+        let indent = rows
+            .iter()
+            .map(|&row| get_indent(&self.buf[row]))
+            .min()
+            .unwrap_or(0);
+
+        if let Some(prefix) = self.language.line_comment(&self.buf) {
+            let prefix = prefix.chars().collect::<Vec<_>>();
+            let commented = rows.iter().all(|&row| {
+                let line = &self.buf[row];
+                line[get_indent(line)..].starts_with(&prefix)
+            });
+            for &row in &rows {
+                if commented {
+                    let col = get_indent(&self.buf[row]);
+                    let mut len = prefix.len();
+                    if self.buf[row].get(col + len) == Some(&' ') {
+                        len += 1;
+                    }
+                    self.remove_at((col, row), len);
+                } else {
+                    let mut text = prefix.clone();
+                    text.push(' ');
+                    self.insert_at((indent, row), &text);
+                }
+            }
+        } else if let Some((open, close)) = self.language.block_comment() {
+            let open = open.chars().collect::<Vec<_>>();
+            let close = close.chars().collect::<Vec<_>>();
+            let first_col = get_indent(&self.buf[first]);
+            let commented = self.buf[first][first_col..].starts_with(&open)
+                && self.buf[last].ends_with(&close)
+                && (first != last || self.buf[first].len() >= first_col + open.len() + close.len());
+            if commented {
+                let mut col = self.buf[last].len() - close.len();
+                let mut len = close.len();
+                if col > first_col + open.len() && self.buf[last][col - 1] == ' ' {
+                    col -= 1;
+                    len += 1;
+                }
+                self.remove_at((col, last), len);
+
+                let mut len = open.len();
+                if self.buf[first].get(first_col + len) == Some(&' ') {
+                    len += 1;
+                }
+                self.remove_at((first_col, first), len);
+            } else {
+                let mut text = vec![' '];
+                text.extend(close);
+                self.insert_at((self.buf[last].len(), last), &text);
+
+                let mut text = open;
+                text.push(' ');
+                self.insert_at((indent, first), &text);
+            }
+        } else {
+            return Some("ERROR: the current language doesn't have comments".into());
+        }
+
+        self.unsaved_changes = true;
+        self.move_cursor(0, 0);
+        None
     }
 
     fn row(&mut self) -> &mut Vec<char> {
@@ -285,7 +1112,18 @@ impl Editor {
         self.update_camera();
     }
 
+    /// The column on screen (before scrolling) where the cursor is, with tabs expanded
+    fn cursor_visual_col(&self) -> usize {
+        let (x, y) = self.cursor.pos;
+        match self.cursor.state {
+            CursorState::Default => visual_col(&self.buf[y], x, self.options.tab_width),
+            CursorState::StatusBar | CursorState::Find => x,
+        }
+    }
+
     fn update_camera(&mut self) {
+        let text_width = self.text_width() as usize;
+        let cursor_x = self.cursor_visual_col();
         let (cx, cy) = &mut self.camera_topleft;
         while self.cursor.pos.1 < *cy {
             *cy -= 1;
@@ -294,10 +1132,10 @@ impl Editor {
             *cy += 1;
         }
 
-        while self.cursor.pos.0 < *cx {
+        while cursor_x < *cx {
             *cx -= 1;
         }
-        while self.cursor.pos.0 >= *cx + self.w as usize {
+        while cursor_x >= *cx + text_width {
             *cx += 1;
         }
     }
@@ -309,28 +1147,159 @@ impl Editor {
         }
 
         match cmd[0] {
-            "quit" => quit(),
+            "quit" => self.quit(),
             "load" => {
                 if cmd.len() != 2 {
-                    return "ERROR: the \"load\" command expects exactly one argument (without spaces)".into();
+                    return "ERROR: the \"load\" command expects exactly one argument (without spaces)".into();
+                }
+
+                self.load_file(cmd[1].into()).err().map(|err| format!("ERROR: {err}"))
+            }
+            "save" => {
+                self.save_file().err().map(|err| format!("ERROR: {err}"))
+            }
+            "save-as" => {
+                if cmd.len() != 2 {
+                    return "ERROR: usage: save-as <path>".into();
+                }
+                self.save_as(cmd[1].into()).err().map(|err| format!("ERROR: {err}"))
+            }
+            "view" => match cmd.len() {
+                1 => {
+                    self.readonly = true;
+                    Some("The buffer is read-only now".into())
+                }
+                2 => match self.load_file(cmd[1].into()) {
+                    Ok(()) => {
+                        self.readonly = true;
+                        None
+                    }
+                    Err(err) => Some(format!("ERROR: {err}")),
+                },
+                _ => Some("ERROR: usage: view [path]".into()),
+            },
+            "lang" => {
+                if cmd.len() != 2 {
+                    return "ERROR: the \"lang\" command expects exactly one argument (without spaces)".into();
+                }
+                if let Some(lang) = lang_from_name(cmd[1]) {
+                    self.language = Box::new(lang);
+                    self.language_name = cmd[1].into();
+                    None
+                } else {
+                    format!("ERROR: unknown language: {}", cmd[1]).into()
+                }
+            }
+            "theme" => {
+                if cmd.len() != 2 {
+                    return "ERROR: the \"theme\" command expects exactly one argument (without spaces)".into();
+                }
+                match Theme::load(cmd[1]) {
+                    Ok(theme) => {
+                        self.theme = theme;
+                        None
+                    }
+                    Err(err) => Some(format!("ERROR: {err}")),
+                }
+            }
+            "set" => {
+                if !(2..=3).contains(&cmd.len()) {
+                    return "ERROR: usage: set <option> [value]".into();
+                }
+                let value = cmd.get(2).copied();
+                match self.options.set(cmd[1], value) {
+                    Ok(()) => {
+                        let _ = self.global_options.set(cmd[1], value);
+                        if cmd[1] == "mouse" {
+                            if let Err(err) = self.update_mouse_capture() {
+                                return format!("ERROR: {err}");
+                            }
+                        }
+                        None
+                    }
+                    Err(err) => Some(format!("ERROR: {err}")),
+                }
+            }
+            "line-ending" => {
+                if cmd.len() != 2 {
+                    return "ERROR: usage: line-ending <lf|crlf|cr>".into();
+                }
+                if self.readonly {
+                    return READONLY_ERROR.into();
+                }
+                match LineEnding::from_name(cmd[1]) {
+                    Some(line_ending) => {
+                        if line_ending != self.line_ending {
+                            self.line_ending = line_ending;
+                            self.unsaved_changes = true;
+                        }
+                        Some(format!("Line endings will be saved as {}", line_ending.name()))
+                    }
+                    None => Some(format!("ERROR: unknown line ending: {}", cmd[1])),
                 }
-
-                self.load_file(cmd[1].into()).err().map(|err| format!("ERROR: {err}"))
             }
-            "save" => {
-                self.save_file().err().map(|err| format!("ERROR: {err}"))
+            "encoding" => {
+                if cmd.len() != 2 {
+                    return "ERROR: usage: encoding <name>".into();
+                }
+                if self.readonly {
+                    return READONLY_ERROR.into();
+                }
+                let Some(encoding) = FileEncoding::from_name(cmd[1]) else {
+                    return format!("ERROR: unknown encoding: {}", cmd[1]);
+                };
+                if encoding != self.encoding {
+                    self.encoding = encoding;
+                    self.unsaved_changes = true;
+                }
+                Some(format!("The file will be saved as {}", encoding.name()))
             }
-            "lang" => {
+            "reopen-with-encoding" => {
                 if cmd.len() != 2 {
-                    return "ERROR: the \"lang\" command expects exactly one argument (without spaces)".into();
+                    return "ERROR: usage: reopen-with-encoding <name>".into();
                 }
-                if let Some(lang) = lang_from_name(cmd[1]) {
-                    self.language = Box::new(lang);
-                    None
-                } else {
-                    format!("ERROR: unknown language: {}", cmd[1]).into()
+                let Some(encoding) = FileEncoding::from_name(cmd[1]) else {
+                    return format!("ERROR: unknown encoding: {}", cmd[1]);
+                };
+                let Some(file_path) = self.file_path.clone() else {
+                    return "ERROR: the buffer isn't a file".into();
+                };
+                if self.unsaved_changes {
+                    return "ERROR: the buffer has unsaved changes".into();
                 }
+                let pos = self.cursor.pos;
+                match self.load_file_as(file_path, Some(encoding)) {
+                    Ok(()) => {
+                        self.cursor.selection_start = None;
+                        self.cursor.pos.1 = pos.1.min(self.buf.len() - 1);
+                        self.move_cursor(0, 0);
+                        None
+                    }
+                    Err(err) => Some(format!("ERROR: {err}")),
+                }
+            }
+            "validate" => match self.language.validate(&self.buf) {
+                None => Some("ERROR: the current language doesn't support validation".into()),
+                Some(Ok(())) => Some("No syntax errors found".into()),
+                Some(Err(err)) => {
+                    self.cursor.selection_start = None;
+                    self.cursor.pos = (err.col, err.line);
+                    self.move_cursor(0, 0);
+                    Some(format!("Syntax error at {}:{}: {}", err.line + 1, err.col + 1, err.msg))
+                }
+            },
+            "hunk-next" => self.jump_to_hunk(true),
+            "hunk-prev" => self.jump_to_hunk(false),
+            "hunk-apply" | "hunk-revert" => match self.apply_hunk(cmd[0] == "hunk-revert") {
+                Ok(msg) => Some(msg),
+                Err(err) => Some(format!("ERROR: {err}")),
+            },
+            "comment" | "rebase-cycle" | "rebase-cycle-back" if self.readonly => {
+                Some(READONLY_ERROR.into())
             }
+            "comment" => self.toggle_comment(),
+            "rebase-cycle" => self.cycle_rebase_action(true),
+            "rebase-cycle-back" => self.cycle_rebase_action(false),
             x if x.starts_with(':') => {
                 let (_, line) = x.split_at(1);
                 let Ok(line) = line.parse::<NonZeroUsize>() else {
@@ -391,19 +1360,29 @@ impl Editor {
                 self.file_path = Some(response);
                 self.save_file()?;
             }
+            PromptType::SaveLossy => match response.as_str() {
+                "y" | "Y" => {
+                    self.lossy = false;
+                    self.save_file()?;
+                }
+                _ => self.set_status("Not saved".into()),
+            },
             PromptType::QuitOnNoSave => {
                 match response.as_str() {
                     "y" | "Y" => {
-                        self.save_file()?;
-                        quit();
+                        if self.save_file()? {
+                            self.quit();
+                        }
                     }
-                    "n" | "N" => quit(),
+                    "n" | "N" => self.quit(),
                     //"c" | "C" => todo!(),
                     _ => self.set_status(format!(
                         "The answer must be one of 'y' or 'n', not {response:?}"
                     )),
                 }
             }
+            PromptType::Recover => self.answer_recovery(&response),
+            PromptType::ExternalChange => self.answer_external_change(&response)?,
             PromptType::Command => {
                 let new_status = self.process_command(&response);
                 self.set_status(new_status);
@@ -439,6 +1418,7 @@ impl Editor {
             return Ok(false);
         }
         let query = query.clone();
+        self.last_search = Some(query.clone());
         let curr_line = self.cursor.pos.1;
 
         let mut found = false;
@@ -462,6 +1442,24 @@ impl Editor {
         Ok(false)
     }
 
+    /// Sets an error status and returns `true` if the buffer is read-only. Typing into the
+    /// command prompt or the search bar is always allowed.
+    fn refuse_edit(&mut self) -> bool {
+        if self.readonly && self.cursor.state == CursorState::Default {
+            self.set_status(READONLY_ERROR.into());
+            return true;
+        }
+        false
+    }
+
+    /// Marks the buffer as modified after a key press that edits text, unless the text was
+    /// typed into the command prompt or the search bar
+    fn mark_modified(&mut self) {
+        if self.cursor.state == CursorState::Default {
+            self.unsaved_changes = true;
+        }
+    }
+
     fn update_selection(&mut self, modifiers: KeyModifiers) {
         if self.cursor.selection_start.is_none() {
             self.cursor.selection_start = Some(self.cursor.pos);
@@ -518,15 +1516,84 @@ impl Editor {
         self.cursor.pos.0 += 1;
     }
 
+    fn auto_pairs_enabled(&self) -> bool {
+        self.options.auto_pairs && self.cursor.state == CursorState::Default
+    }
+
+    /// Inserts a typed character, closing brackets and quotes automatically: an opening
+    /// character gets its closer inserted after it (or wraps the selection), and typing a closer
+    /// that is already right after the cursor just moves over it.
+    fn type_char(&mut self, ch: char) {
+        if !self.auto_pairs_enabled() {
+            self.add_char(ch);
+            return;
+        }
+
+        let close = brackets::closing(&*self.language, ch);
+        if let (Some(close), Some(sel)) = (close, self.cursor.selection_start) {
+            self.wrap_selection(sel, ch, close);
+            return;
+        }
+
+        let (x, y) = self.cursor.pos;
+        if self.cursor.selection_start.is_none()
+            && brackets::is_closing(&*self.language, ch)
+            && self.buf[y].get(x) == Some(&ch)
+        {
+            self.cursor.pos.0 += 1;
+            return;
+        }
+
+        let pair = close.filter(|_| {
+            self.cursor.selection_start.is_none()
+                && brackets::should_auto_pair(&*self.language, &self.buf[y], x, ch)
+        });
+        self.add_char(ch);
+        if let Some(close) = pair {
+            self.buf[y].insert(x + 1, close);
+        }
+    }
+
+    /// Puts `open` before and `close` after the selection, keeping the text selected
+    fn wrap_selection(&mut self, sel: Pos, open: char, close: char) {
+        let ((sx, sy), (cx, cy)) = Cursor::minmax_pos(sel, self.cursor.pos);
+        let close_x = (cx + 1).min(self.buf[cy].len());
+        self.buf[cy].insert(close_x, close);
+        let open_x = sx.min(self.buf[sy].len());
+        self.buf[sy].insert(open_x, open);
+
+        let shift = |(x, y): Pos| if y == sy { (x + 1, y) } else { (x, y) };
+        self.cursor.selection_start = Some(shift(sel));
+        self.cursor.pos = shift(self.cursor.pos);
+        self.unsaved_changes = true;
+    }
+
+    /// Removes the closing character after the cursor if the one before it opens it, so that
+    /// backspacing inside an empty pair deletes both
+    fn delete_empty_pair(&mut self) {
+        if !self.auto_pairs_enabled() || self.cursor.selection_start.is_some() {
+            return;
+        }
+        let (x, y) = self.cursor.pos;
+        let row = &self.buf[y];
+        let (Some(&prev), Some(&next)) = (x.checked_sub(1).and_then(|i| row.get(i)), row.get(x))
+        else {
+            return;
+        };
+        if brackets::closing(&*self.language, prev) == Some(next) {
+            self.buf[y].remove(x);
+        }
+    }
+
     fn backspace(&mut self) -> Option<char> {
         if self.cursor.pos.0 != 0 {
             let mut x = self.cursor.pos.0;
+            let width = self.options.indent_width;
             let row = self.row();
-            if row[..x].ends_with(&"    ".chars().collect::<Vec<char>>()) {
-                row.remove(x - 1);
-                row.remove(x - 2);
-                row.remove(x - 3);
-                x -= 3;
+            // Remove a whole level of space indentation at once
+            if x >= width && row[x - width..x].iter().all(|&ch| ch == ' ') {
+                row.drain(x - width + 1..x);
+                x -= width - 1;
             }
             x -= 1;
             let res = row.remove(x);
@@ -606,10 +1673,10 @@ impl Editor {
         };
         let ((sx, sy), (cx, cy)) = Cursor::minmax_pos(sel, self.cursor.pos);
 
-        if self.clipboard.is_none() {
-            self.clipboard = Some(Vec::new());
+        if let Some(clipboard) = &mut self.clipboard {
+            clipboard.clear();
         } else {
-            self.clipboard.as_mut().unwrap().clear();
+            self.clipboard = Some(Vec::new());
         }
 
         if self.buf[cy].is_empty() {
@@ -683,12 +1750,16 @@ impl Editor {
             self.status = Vec::new();
         }
         self.log(format!("Got event: {e:?}"));
+        self.lex_stale = true;
+        if matches!(e, Event::Key(_) | Event::Mouse(_) | Event::Paste(_)) {
+            self.last_input = Instant::now();
+        }
         match e {
             Event::Resize(w, h) => {
                 self.display.resize(w, h);
-                self.w = w - UI_WIDTH;
                 self.h = h - UI_HEIGHT;
             }
+            Event::FocusLost => self.autosave(),
 
             Event::Key(KeyEvent {
                 code: KeyCode::Char('q'),
@@ -696,13 +1767,17 @@ impl Editor {
                 kind: KeyEventKind::Press | KeyEventKind::Repeat,
                 ..
             }) => {
-                if self.unsaved_changes {
+                if self.reprompt_diff() {
+                    return Ok(false);
+                }
+                // Buffers without a file that are written to stdout aren't lost
+                if self.unsaved_changes && (self.output.is_none() || self.file_path.is_some()) {
                     self.set_status_prompt(
                         "You have unsaved changes. Save now? (y/n) ".into(),
                         PromptType::QuitOnNoSave,
                     );
                 } else {
-                    quit();
+                    self.quit();
                 }
             }
             Event::Key(KeyEvent {
@@ -710,7 +1785,12 @@ impl Editor {
                 modifiers: KeyModifiers::CONTROL,
                 kind: KeyEventKind::Press | KeyEventKind::Repeat,
                 ..
-            }) => self.save_file()?,
+            }) => {
+                // Failing to save shouldn't close the editor and lose the changes
+                if let Err(err) = self.save_file() {
+                    self.set_status(format!("ERROR: {err}"));
+                }
+            }
             Event::Key(KeyEvent {
                 code: KeyCode::Char('c'),
                 modifiers: KeyModifiers::CONTROL,
@@ -722,32 +1802,70 @@ impl Editor {
                 modifiers: KeyModifiers::CONTROL,
                 kind: KeyEventKind::Press | KeyEventKind::Repeat,
                 ..
-            }) => self.paste_text(),
+            }) => {
+                if self.refuse_edit() {
+                    return Ok(false);
+                }
+                self.paste_text();
+            }
             Event::Key(KeyEvent {
                 code: KeyCode::Char('f'),
                 modifiers: KeyModifiers::CONTROL,
                 kind: KeyEventKind::Press | KeyEventKind::Repeat,
                 ..
             }) => self.begin_find(),
+            // Ctrl+] is reported as Ctrl+5 by terminals without keyboard enhancements
+            Event::Key(KeyEvent {
+                code: KeyCode::Char(']' | '5'),
+                modifiers: KeyModifiers::CONTROL,
+                kind: KeyEventKind::Press | KeyEventKind::Repeat,
+                ..
+            }) => {
+                if let Some((_, other)) = self.matching_bracket() {
+                    self.cursor.selection_start = None;
+                    self.cursor.pos = other;
+                    self.update_camera();
+                }
+            }
+            // Ctrl+/ is reported as Ctrl+7 by terminals without keyboard enhancements
+            Event::Key(KeyEvent {
+                code: KeyCode::Char('/' | '7'),
+                modifiers: KeyModifiers::CONTROL,
+                kind: KeyEventKind::Press | KeyEventKind::Repeat,
+                ..
+            }) => {
+                if self.refuse_edit() {
+                    return Ok(false);
+                }
+                if let Some(status) = self.toggle_comment() {
+                    self.set_status(status);
+                }
+            }
             Event::Key(KeyEvent {
                 code: KeyCode::Char(ch),
                 modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
                 kind: KeyEventKind::Press | KeyEventKind::Repeat,
                 ..
             }) => {
-                self.unsaved_changes = true;
-
-                if self.language.should_dedent(ch) {
-                    let curr_indent = Self::get_indent(self.row());
-                    if curr_indent >= 4 {
-                        let target_indent = curr_indent - 4;
-                        while Self::get_indent(self.row()) > target_indent {
+                if self.refuse_edit() {
+                    return Ok(false);
+                }
+                self.mark_modified();
+
+                let x = self.cursor.pos.0;
+                let at_line_start = self.row()[..x].iter().all(|ch| ch.is_whitespace());
+                if at_line_start && self.language.should_dedent(ch) {
+                    let curr_indent = get_indent(self.row());
+                    let unit = self.indent_unit().len();
+                    if curr_indent >= unit {
+                        let target_indent = curr_indent - unit;
+                        while get_indent(self.row()) > target_indent {
                             self.backspace();
                         }
                     }
                 }
 
-                self.add_char(ch);
+                self.type_char(ch);
             }
             Event::Key(KeyEvent {
                 code: KeyCode::Enter,
@@ -760,6 +1878,9 @@ impl Editor {
                     CursorState::StatusBar => return self.handle_status_prompt(),
                     CursorState::Find => return self.handle_find(),
                 }
+                if self.refuse_edit() {
+                    return Ok(false);
+                }
 
                 self.unsaved_changes = true;
                 if self.cursor.selection_start.is_some() {
@@ -779,23 +1900,31 @@ impl Editor {
                     *y += 1;
                     *x = 0;
                 } else {
-                    let indent = Self::get_indent(self.row());
+                    let row = self.row();
+                    let indent = row[..get_indent(row)].to_vec();
 
                     self.buf.insert(self.cursor.pos.1 + 1, Vec::new());
 
                     self.cursor.pos.1 += 1;
                     self.cursor.pos.0 = 0;
 
-                    let target_indent = if self
+                    // Keep the previous line's indentation as-is, so that tabs stay tabs
+                    for ch in indent {
+                        self.add_char(ch);
+                    }
+                    if self
                         .language
                         .should_indent(&self.buf[self.cursor.pos.1 - 1])
                     {
-                        indent + 4
-                    } else {
-                        indent
-                    };
-                    while Self::get_indent(self.row()) < target_indent {
-                        self.add_char(' ');
+                        for ch in self.indent_unit() {
+                            self.add_char(ch);
+                        }
+                    }
+                    let continuation = self
+                        .language
+                        .line_continuation(&self.buf[self.cursor.pos.1 - 1]);
+                    for ch in continuation.into_iter().flatten() {
+                        self.add_char(ch);
                     }
                 }
                 self.move_cursor(0, 0);
@@ -806,9 +1935,30 @@ impl Editor {
                 kind: KeyEventKind::Press | KeyEventKind::Repeat,
                 ..
             }) => {
-                for _ in 0..4 {
-                    self.add_char(' ');
+                if self.refuse_edit() {
+                    return Ok(false);
+                }
+                if self.cursor.state == CursorState::Default
+                    && self.cursor.selection_start.is_some()
+                {
+                    self.indent_selection();
+                } else {
+                    for ch in self.indent_unit() {
+                        self.add_char(ch);
+                    }
+                }
+                self.mark_modified();
+            }
+            Event::Key(KeyEvent {
+                code: KeyCode::BackTab,
+                modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                kind: KeyEventKind::Press | KeyEventKind::Repeat,
+                ..
+            }) if self.cursor.state == CursorState::Default => {
+                if self.refuse_edit() {
+                    return Ok(false);
                 }
+                self.dedent_selection();
             }
             Event::Key(KeyEvent {
                 // Ctrl+Backspace == Ctrl+H for some reason
@@ -818,6 +1968,9 @@ impl Editor {
                 ..
             }) if modifiers == KeyModifiers::NONE || modifiers == KeyModifiers::CONTROL => {
                 assert!(self.cursor.pos.1 < self.buf.len());
+                if self.refuse_edit() {
+                    return Ok(false);
+                }
 
                 if self.cursor.selection_start.is_some() {
                     self.add_char('\0');
@@ -825,9 +1978,10 @@ impl Editor {
                 if modifiers.contains(KeyModifiers::CONTROL) {
                     self.backspace_word();
                 } else {
+                    self.delete_empty_pair();
                     self.backspace();
                 }
-                self.unsaved_changes = true;
+                self.mark_modified();
             }
             Event::Key(KeyEvent {
                 code: KeyCode::Delete,
@@ -836,6 +1990,9 @@ impl Editor {
                 ..
             }) if modifiers == KeyModifiers::NONE || modifiers == KeyModifiers::CONTROL => {
                 assert!(self.cursor.pos.1 < self.buf.len());
+                if self.refuse_edit() {
+                    return Ok(false);
+                }
 
                 if modifiers.contains(KeyModifiers::CONTROL) {
                     self.move_cursor_word(1);
@@ -865,7 +2022,7 @@ impl Editor {
                         }
                     }
                 }
-                self.unsaved_changes = true;
+                self.mark_modified();
             }
 
             Event::Key(KeyEvent {
@@ -930,12 +2087,36 @@ impl Editor {
                 self.update_selection(modifiers);
                 self.cursor.pos.0 = self.row().len();
             }
+            Event::Mouse(MouseEvent {
+                kind:
+                    kind @ (MouseEventKind::Down(MouseButton::Left)
+                    | MouseEventKind::Drag(MouseButton::Left)),
+                column,
+                row,
+                ..
+            }) if self.cursor.state == CursorState::Default => {
+                let Some(pos) = self.pos_at_screen(column, row) else {
+                    return Ok(false);
+                };
+                if let MouseEventKind::Drag(_) = kind {
+                    if self.cursor.selection_start.is_none() {
+                        self.cursor.selection_start = Some(self.cursor.pos);
+                    }
+                } else {
+                    self.cursor.selection_start = None;
+                }
+                self.cursor.pos = pos;
+                self.update_camera();
+            }
             Event::Key(KeyEvent {
                 code: KeyCode::Esc,
                 modifiers: KeyModifiers::NONE,
                 kind: KeyEventKind::Press | KeyEventKind::Repeat,
                 ..
             }) => {
+                if self.reprompt_diff() {
+                    return Ok(false);
+                }
                 self.set_status_prompt("Command: ".into(), PromptType::Command);
             }
             _ => {}
@@ -959,16 +2140,7 @@ impl Editor {
         // TODO: this should really be in crossterm-display
         for x in 0..self.display.w as usize {
             for y in 0..self.display.h as usize {
-                self.display.write(
-                    x,
-                    y,
-                    Cell {
-                        ch: ' ',
-                        fg: Color::White,
-                        bg: BLACK,
-                        attr: Attribute::Reset,
-                    },
-                );
+                self.display.write(x, y, self.theme.cell("text", ' '));
             }
         }
 
@@ -980,9 +2152,10 @@ impl Editor {
                 SetCursorStyle::BlinkingBlock
             })?;
 
-        self.render_line_numbers();
+        self.lex_visible_rows();
+        self.render_gutter();
         self.render_buf();
-        self.render_file_path();
+        self.render_status_line();
         self.render_status_bar();
 
         self.display.render()?;
@@ -991,7 +2164,10 @@ impl Editor {
 
         let (x, y) = self.cursor.pos;
         let (x, y) = match self.cursor.state {
-            CursorState::Default => (x - cx + UI_WIDTH as usize, y - cy),
+            CursorState::Default => (
+                self.cursor_visual_col() - cx + self.gutter_width() as usize,
+                y - cy,
+            ),
             CursorState::StatusBar | CursorState::Find => {
                 (x - cx + self.status_prompt.len(), (self.h + 1) as usize)
             }
@@ -1005,36 +2181,79 @@ impl Editor {
         Ok(())
     }
 
-    fn render_line_numbers(&mut self) {
+    /// The buffer position shown at the screen cell `(column, row)`, if it's in the text area
+    fn pos_at_screen(&self, column: u16, row: u16) -> Option<Pos> {
+        if row >= self.h || column < self.gutter_width() {
+            return None;
+        }
+        let (cx, cy) = self.camera_topleft;
+        let y = (row as usize + cy).min(self.buf.len() - 1);
+        let vx = (column - self.gutter_width()) as usize + cx;
+        Some((col_at_visual(&self.buf[y], vx, self.options.tab_width), y))
+    }
+
+    /// Width of the gutter: the sign column, the line numbers and a space before the text
+    fn gutter_width(&self) -> u16 {
+        let digits = self.buf.len().to_string().len().max(3);
+        digits as u16 + 2
+    }
+
+    fn text_width(&self) -> u16 {
+        self.display.w.saturating_sub(self.gutter_width())
+    }
+
+    /// The marker shown next to `row`. Languages take precedence over plugins, which take
+    /// precedence over search matches.
+    fn sign_cell(&self, row: usize) -> Cell {
+        if let Some((ch, scope)) = self.lexed.as_ref().and_then(|lexed| lexed.sign(row)) {
+            let sign = self.theme.scope_cell(scope, ch);
+            return Cell {
+                fg: sign.fg,
+                attr: sign.attr,
+                ..self.theme.cell("ui.sign", ch)
+            };
+        }
+        if let Some(ch) = self
+            .plugins
+            .iter()
+            .find_map(|plugin| plugin.signs.get(&row))
+        {
+            return self.theme.cell("ui.sign.plugin", *ch);
+        }
+        let matches_search = self.last_search.as_ref().is_some_and(|query| {
+            !query.is_empty() && self.buf[row].windows(query.len()).any(|w| w == query)
+        });
+        if matches_search {
+            return self.theme.cell("ui.sign.search", '*');
+        }
+        self.theme.cell("ui.sign", ' ')
+    }
+
+    fn render_gutter(&mut self) {
         let (_, cy) = self.camera_topleft;
+        let digits = self.gutter_width() as usize - 2;
 
-        for y in 0..self.h {
-            let y = y as usize;
-            let num = y + cy;
-
-            let num_str = lpad((num + 1).to_string(), 3);
-            let num_str = String::from(&num_str[num_str.len() - 3..]);
-
-            for x in 0..(UI_WIDTH - 1) {
-                let x = x as usize;
-                self.display.write(
-                    x,
-                    y,
-                    Cell {
-                        ch: num_str.chars().nth(x).unwrap_or(' '),
-                        fg: if num == self.cursor.pos.1 {
-                            Color::White
-                        } else {
-                            Color::Grey
-                        },
-                        bg: Color::DarkGrey,
-                        attr: if num == self.cursor.pos.1 {
-                            Attribute::Bold
-                        } else {
-                            Attribute::Reset
-                        },
-                    },
-                )
+        for y in 0..self.h as usize {
+            let row = y + cy;
+            if row >= self.buf.len() {
+                for x in 0..=digits {
+                    self.display.write(x, y, self.theme.cell("ui.gutter", ' '));
+                }
+                continue;
+            }
+
+            self.display.write(0, y, self.sign_cell(row));
+
+            let curr_row = self.cursor.pos.1;
+            let (num, key) = if row == curr_row {
+                (row + 1, "ui.gutter.current")
+            } else if self.options.relative_numbers {
+                (row.abs_diff(curr_row), "ui.gutter")
+            } else {
+                (row + 1, "ui.gutter")
+            };
+            for (x, ch) in lpad(num.to_string(), digits).chars().enumerate() {
+                self.display.write(x + 1, y, self.theme.cell(key, ch));
             }
         }
     }
@@ -1061,58 +2280,298 @@ impl Editor {
         }
     }
 
+    /// Lexes `rows` of the buffer, unless the words from last time still cover them
+    fn lex_rows(&mut self, rows: Range<usize>) {
+        let rows = rows.start.min(self.buf.len())..rows.end.min(self.buf.len());
+        if std::mem::take(&mut self.lex_stale) {
+            let hash = swap::hash(&self.buf);
+            if self.lexed.as_ref().is_some_and(|lexed| lexed.hash != hash) {
+                self.lexed = None;
+            }
+        }
+        let up_to_date = self.lexed.as_ref().is_some_and(|lexed| {
+            lexed.language == self.language_name
+                && lexed.rows().start <= rows.start
+                && rows.end <= lexed.rows().end
+        });
+        if !up_to_date {
+            self.lexed = Some(LexedRows::new(
+                &*self.language,
+                self.language_name.clone(),
+                &self.buf,
+                swap::hash(&self.buf),
+                rows,
+            ));
+        }
+    }
+
+    /// Lexes what's on screen, along with as much around it and the cursor as finding matching
+    /// brackets and the bracket depth needs
+    fn lex_visible_rows(&mut self) {
+        let (_, cy) = self.camera_topleft;
+        let y = self.cursor.pos.1;
+        let start = cy.min(y).saturating_sub(brackets::MAX_SCAN_ROWS);
+        let end = (cy + self.h as usize).max(y + 1) + brackets::MAX_SCAN_ROWS;
+        self.lex_rows(start..end);
+    }
+
+    /// The bracket under the cursor (or right before it) and the one matching it
+    fn matching_bracket(&mut self) -> Option<(Pos, Pos)> {
+        if self.cursor.state != CursorState::Default {
+            return None;
+        }
+        self.lex_visible_rows();
+        let lexed = self.lexed.as_ref()?;
+        let (x, y) = self.cursor.pos;
+        [Some(x), x.checked_sub(1)]
+            .into_iter()
+            .flatten()
+            .find_map(|x| {
+                let other = brackets::find_match(lexed, &self.buf, (x, y))?;
+                Some(((x, y), other))
+            })
+    }
+
+    /// Visual columns where indentation guides are drawn on `row`. Blank lines take them from the
+    /// surrounding lines so that the guides aren't interrupted.
+    fn indent_guides(&self, row: usize) -> Vec<usize> {
+        let is_blank = |line: &&Vec<char>| line.iter().all(|ch| ch.is_whitespace());
+        let mut line = &self.buf[row];
+        if is_blank(&line) {
+            let prev = self.buf[..row].iter().rev().find(|line| !is_blank(line));
+            let next = self.buf[row + 1..].iter().find(|line| !is_blank(line));
+            let (Some(prev), Some(next)) = (prev, next) else {
+                return Vec::new();
+            };
+            line = if get_indent(prev) < get_indent(next) {
+                prev
+            } else {
+                next
+            };
+        }
+
+        let mut guides = Vec::new();
+        let mut spaces = 0;
+        let mut vx = 0;
+        for &ch in &line[..get_indent(line)] {
+            if ch == '\t' {
+                guides.push(vx);
+                spaces = 0;
+            } else {
+                if spaces % self.options.indent_width == 0 {
+                    guides.push(vx);
+                }
+                spaces += 1;
+            }
+            vx += char_width(ch, vx, self.options.tab_width);
+        }
+        guides
+    }
+
     fn render_buf(&mut self) {
         let (cx, cy) = self.camera_topleft;
 
+        let bracket_match = self.matching_bracket();
+        let Some(lexed) = &self.lexed else {
+            return;
+        };
+        let mut depth = if self.options.rainbow_brackets {
+            brackets::depth_at(lexed, &self.buf, cy)
+        } else {
+            0
+        };
+
         for y in 0..self.h as usize {
             let row_idx = y + cy;
             if row_idx >= self.buf.len() {
                 break;
             }
-            let mut words = self
-                .language
-                .split_words(&self.buf[row_idx])
-                .into_iter()
-                .peekable();
+            let row_words = lexed.words(row_idx).unwrap_or_default();
+            // The depth of every bracket on the row, including the ones scrolled out of view
+            let mut rainbow = Vec::new();
+            if self.options.rainbow_brackets {
+                for (col, bracket) in brackets::brackets(&self.buf[row_idx], row_words) {
+                    if brackets::is_open(bracket) {
+                        rainbow.push((col, depth));
+                        depth += 1;
+                    } else {
+                        depth = depth.saturating_sub(1);
+                        rainbow.push((col, depth));
+                    }
+                }
+            }
+            let mut words = row_words.iter().peekable();
+
+            let row = &self.buf[row_idx];
+            let trailing_start =
+                row.len() - row.iter().rev().take_while(|ch| ch.is_whitespace()).count();
+            let guides = if self.options.indent_guides {
+                self.indent_guides(row_idx)
+            } else {
+                Vec::new()
+            };
+
+            // The character shown in each cell of the line, with tabs taking up several cells
+            let mut layout = Vec::new();
+            for (col, &ch) in row.iter().enumerate() {
+                let width = char_width(ch, layout.len(), self.options.tab_width);
+                layout.extend(std::iter::repeat_n(col, width));
+            }
+
+            let gutter_width = self.gutter_width() as usize;
+            for x in 0..self.text_width() as usize {
+                let vx = x + cx;
+                let x = x + gutter_width;
+                // Cells past the end of the line still count as columns for the selection
+                let ch_idx = layout
+                    .get(vx)
+                    .copied()
+                    .unwrap_or(row.len() + vx.saturating_sub(layout.len()));
+                let first_cell = vx == 0 || layout.get(vx - 1) != Some(&ch_idx);
+                let raw_ch = get2d(&self.buf, row_idx, ch_idx).copied();
+                let ch = match raw_ch {
+                    // Printing a tab would move the terminal's cursor
+                    Some('\t') | None => ' ',
+                    Some(ch) => ch,
+                };
 
-            for x in 0..self.w {
-                let x = (x + UI_WIDTH) as usize;
-                let ch_idx = x + cx - UI_WIDTH as usize;
-                let ch = *get2d(&self.buf, row_idx, ch_idx).unwrap_or(&' ');
+                let scope = get_curr_word(&mut words, ch_idx)
+                    .map(|w| w.scope)
+                    .unwrap_or(Scope::Text);
+                let mut cell = self.theme.scope_cell(scope, ch);
 
-                let mut bg = BLACK;
-                let mut fg = get_curr_word(&mut words, ch_idx)
-                    .map(|w| w.color)
-                    .unwrap_or(Color::White);
+                if guides.contains(&vx) && raw_ch.is_none_or(char::is_whitespace) {
+                    cell = self.theme.cell("ui.indent_guide", '│');
+                } else if let Some(glyph) = raw_ch
+                    .filter(|_| self.options.whitespace)
+                    .and_then(whitespace_glyph)
+                {
+                    let glyph = if first_cell { glyph } else { ' ' };
+                    let key = if ch_idx >= trailing_start {
+                        "ui.whitespace.trailing"
+                    } else {
+                        "ui.whitespace"
+                    };
+                    cell = self.theme.cell(key, glyph);
+                }
 
-                if self.selected(x + cx - UI_WIDTH as usize, y + cy) {
-                    (fg, bg) = (bg, fg);
+                let bracket_depth = rainbow
+                    .binary_search_by_key(&ch_idx, |&(col, _)| col)
+                    .ok()
+                    .filter(|_| first_cell)
+                    .map(|i| rainbow[i].1);
+                if let Some(bracket_depth) = bracket_depth {
+                    let key = format!("ui.rainbow.{}", bracket_depth % 6 + 1);
+                    cell.fg = self.theme.cell(&key, ch).fg;
+                }
+                if bracket_match.is_some_and(|(a, b)| [a, b].contains(&(ch_idx, row_idx))) {
+                    let highlight = self.theme.cell("ui.bracket_match", ch);
+                    cell.bg = highlight.bg;
+                    cell.attr = highlight.attr;
+                }
+
+                if self.options.max_line_length == Some(vx) {
+                    cell.bg = self.theme.cell("ui.ruler", ch).bg;
                 }
 
-                let attr = get_curr_word(&mut words, ch_idx)
-                    .map(|w| w.attr)
-                    .unwrap_or(Attribute::Reset);
+                if self.selected(ch_idx, row_idx) {
+                    (cell.fg, cell.bg) = (cell.bg, cell.fg);
+                }
 
-                let cell = Cell { ch, fg, bg, attr };
                 self.display.write(x, y, cell);
             }
         }
     }
 
-    fn render_file_path(&mut self) {
+    /// Text of the status line segment `name`, or `None` if it has nothing to show
+    fn status_segment(&mut self, name: &str) -> Option<String> {
+        let (x, y) = self.cursor.pos;
+        match name {
+            "modified" => self.unsaved_changes.then(|| "[+]".into()),
+            "readonly" => self.readonly.then(|| "[RO]".into()),
+            "path" => Some(
+                self.file_path
+                    .clone()
+                    .unwrap_or("<temporary buffer>".into()),
+            ),
+            "language" => Some(self.language_name.clone()),
+            "line-ending" => Some(self.line_ending.name().into()),
+            "encoding" => Some(self.encoding.name()),
+            "position" => Some(format!("{}:{}", y + 1, x + 1)),
+            "percent" => Some(format!("{}%", (y + 1) * 100 / self.buf.len())),
+            "selection" => {
+                let sel = self.cursor.selection_start?;
+                let ((sx, sy), (cx, cy)) = Cursor::minmax_pos(sel, self.cursor.pos);
+                if sy == cy {
+                    Some(format!("{} chars", cx - sx + 1))
+                } else {
+                    Some(format!("{} lines", cy - sy + 1))
+                }
+            }
+            name => self.plugin_segment(name),
+        }
+    }
+
+    fn plugin_segment(&mut self, name: &str) -> Option<String> {
+        unsafe {
+            for i in 0..self.plugins.len() {
+                let plugin_ptr = &mut self.plugins[i] as *mut Plugin;
+                let segment = (*plugin_ptr)
+                    .segments
+                    .iter()
+                    .find(|(segment, _, _)| segment == name);
+                if let Some(&(_, callback, data)) = segment {
+                    let api_ptr = &mut Api::new(self, plugin_ptr) as *mut _;
+                    let text: String = callback(api_ptr, data).into();
+                    return (!text.is_empty()).then_some(text);
+                }
+            }
+        }
+        None
+    }
+
+    fn render_status_line(&mut self) {
         let y = self.display.h as usize - 2;
+        let w = self.display.w as usize;
+
+        let config = self.options.status_line.clone();
+        let (left_names, right_names) = config.split_once('|').unwrap_or((&config, ""));
+        let mut left_names = left_names.split(',').map(str::trim).collect::<Vec<_>>();
+        let right_names = right_names.split(',').map(str::trim).collect::<Vec<_>>();
+
+        // Plugin segments that aren't in the config would never show up otherwise
+        let plugin_names = self
+            .plugins
+            .iter()
+            .flat_map(|plugin| plugin.segments.iter().map(|(name, _, _)| name.clone()))
+            .collect::<Vec<_>>();
+        left_names.extend(
+            plugin_names
+                .iter()
+                .filter(|name| !config.split([',', '|']).any(|n| n.trim() == *name))
+                .map(String::as_str),
+        );
 
-        let file_path = self
-            .file_path
-            .clone()
-            .unwrap_or("<temporary buffer>".into());
-        for x in 0..self.display.w as usize {
-            let cell = Cell {
-                ch: file_path.chars().nth(x).unwrap_or(' '),
-                fg: BLACK,
-                bg: Color::White,
-                attr: Attribute::Reset,
-            };
+        let mut render_group = |names: &[&str]| {
+            names
+                .iter()
+                .filter(|name| !name.is_empty())
+                .filter_map(|name| self.status_segment(name))
+                .collect::<Vec<_>>()
+                .join("  ")
+        };
+        let left = render_group(&left_names);
+        let right = render_group(&right_names);
+
+        let mut line = left.chars().collect::<Vec<_>>();
+        let right_len = right.chars().count() + 1;
+        line.resize(w.saturating_sub(right_len).max(line.len() + 1), ' ');
+        line.extend(right.chars());
+
+        for x in 0..w {
+            let cell = self
+                .theme
+                .cell("ui.statusline", line.get(x).copied().unwrap_or(' '));
             self.display.write(x, y, cell);
         }
     }
@@ -1126,36 +2585,68 @@ impl Editor {
             .chain(self.status.iter().copied())
             .chain(std::iter::repeat(' '));
         for (x, ch) in (0..self.display.w as usize).zip(status_iter) {
-            let cell = Cell {
-                ch,
-                fg: Color::White,
-                bg: BLACK,
-                attr: Attribute::Reset,
-            };
+            let cell = self.theme.cell("ui.status", ch);
             self.display.write(x, y, cell);
         }
     }
 }
 
-fn get_curr_word(
-    words: &mut std::iter::Peekable<std::vec::IntoIter<Word>>,
+fn get_curr_word<'a>(
+    words: &mut std::iter::Peekable<std::slice::Iter<'a, Word>>,
     ch_idx: usize,
-) -> Option<Word> {
-    let w = words.peek()?;
-    let w = w.clone();
-    let Word {
-        col: pos,
-        text: ref word,
-        ..
-    } = w;
-    if ch_idx == (pos + word.len()) {
+) -> Option<&'a Word> {
+    // Skip words that end before `ch_idx`. This matters when the camera is scrolled horizontally
+    // or when two words touch each other (e.g. a string followed by a `${` in JS).
+    while words
+        .peek()
+        .filter(|w| ch_idx >= w.col + w.text.chars().count())
+        .is_some()
+    {
         words.next();
     }
-    if ch_idx >= pos && ch_idx < (pos + word.len()) {
-        Some(w)
+    let w = *words.peek()?;
+    (ch_idx >= w.col).then_some(w)
+}
+
+/// The glyph shown for `ch` when whitespace is visible
+fn whitespace_glyph(ch: char) -> Option<char> {
+    match ch {
+        ' ' => Some('·'),
+        '\t' => Some('→'),
+        '\u{a0}' => Some('⍽'),
+        _ => None,
+    }
+}
+
+/// How many cells `ch` takes up when it starts at visual column `vx`
+fn char_width(ch: char, vx: usize, tab_width: usize) -> usize {
+    if ch == '\t' {
+        tab_width - vx % tab_width
     } else {
-        None
+        1
+    }
+}
+
+/// The visual column where `line[col]` starts, with tabs expanded to the next tab stop
+fn visual_col(line: &[char], col: usize, tab_width: usize) -> usize {
+    let end = col.min(line.len());
+    let vx = line[..end]
+        .iter()
+        .fold(0, |vx, &ch| vx + char_width(ch, vx, tab_width));
+    vx + (col - end)
+}
+
+/// The column of the character shown at visual column `vx`, or the end of the line if `vx` is
+/// past it
+fn col_at_visual(line: &[char], vx: usize, tab_width: usize) -> usize {
+    let mut x = 0;
+    for (col, &ch) in line.iter().enumerate() {
+        x += char_width(ch, x, tab_width);
+        if vx < x {
+            return col;
+        }
     }
+    line.len()
 }
 
 fn lpad(mut s: String, n: usize) -> String {
@@ -1192,11 +2683,17 @@ fn logger(port: u16) -> std::io::Result<Sender<String>> {
     Ok(sender)
 }
 
-fn quit() -> ! {
+fn restore_terminal() {
+    let _ = std::io::stdout().execute(DisableMouseCapture);
+    let _ = std::io::stdout().execute(DisableFocusChange);
     let _ = terminal::disable_raw_mode();
     let _ = std::io::stdout().execute(style::SetColors(Colors::new(Color::Reset, Color::Reset)));
     let _ = std::io::stdout().execute(Clear(terminal::ClearType::All));
     let _ = std::io::stdout().execute(MoveTo(0, 0));
+}
+
+fn quit() -> ! {
+    restore_terminal();
     exit(0);
 }
 
@@ -1211,20 +2708,39 @@ fn main() -> Result<(), std::io::Error> {
     #[cfg(debug_assertions)]
     editor.enable_logging(6969)?;
 
-    while args.next_if_eq("--plugin").is_some() {
-        let plugin = args.next().expect("plugin name should be provided");
-        if let Err(err) = editor.load_plugin(plugin.clone()) {
-            eprintln!(
-                "Failed to load plugin {}: {}",
-                plugin,
-                err.into_string().unwrap()
-            );
-            std::process::exit(1);
+    // Elsewhere the editor can't draw on the terminal while stdout is redirected, so only
+    // `--stdout` turns this on
+    let mut to_stdout = cfg!(unix) && !std::io::stdout().is_terminal();
+    loop {
+        if args.next_if_eq("--plugin").is_some() {
+            let plugin = args.next().expect("plugin name should be provided");
+            if let Err(err) = editor.load_plugin(plugin.clone()) {
+                eprintln!(
+                    "Failed to load plugin {}: {}",
+                    plugin,
+                    err.into_string().unwrap()
+                );
+                std::process::exit(1);
+            }
+        } else if args.next_if_eq("--readonly").is_some() {
+            editor.global_options.readonly = true;
+        } else if args.next_if_eq("--stdout").is_some() {
+            to_stdout = true;
+        } else {
+            break;
         }
     }
 
-    if let Some(file_path) = args.next() {
+    let file_path = args.next();
+    if pipe::should_read_stdin(file_path.as_deref()) {
+        editor.load_stdin(&pipe::read_stdin()?);
+    } else if let Some(file_path) = file_path {
         editor.load_file(file_path)?;
+    } else {
+        editor.readonly = editor.global_options.readonly;
+    }
+    if to_stdout {
+        editor.output = Some(pipe::redirect_stdout_to_tty()?);
     }
 
     terminal::enable_raw_mode()?;
@@ -1233,15 +2749,41 @@ fn main() -> Result<(), std::io::Error> {
         .stdout
         .queue(terminal::Clear(terminal::ClearType::All))?;
     editor.display.stdout.queue(cursor::MoveTo(0, 0))?;
+    editor.update_mouse_capture()?;
+    editor.display.stdout.queue(EnableFocusChange)?;
     #[cfg(unix)]
     editor.display.stdout.queue(PushKeyboardEnhancementFlags(
         KeyboardEnhancementFlags::REPORT_EVENT_TYPES,
     ))?;
 
+    // Leave the terminal usable and the panic message readable if something goes wrong
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        default_hook(info);
+    }));
+
     loop {
-        if poll(polling_rate)? {
-            editor.handle_event(read()?)?;
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            if poll(polling_rate)? {
+                editor.handle_event(read()?)?;
+            }
+            editor.update_swap();
+            editor.check_external_change();
+            editor.autosave_when_idle();
+            editor.render()
+        }));
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => {
+                restore_terminal();
+                editor.dump_buffers();
+                return Err(err);
+            }
+            Err(_) => {
+                editor.dump_buffers();
+                exit(101);
+            }
         }
-        editor.render()?;
     }
 }
@@ -0,0 +1,149 @@
+/// Editor settings that can be changed at runtime with the `set` command
+#[derive(Clone)]
+pub struct Options {
+    /// Show the distance to the cursor line instead of absolute line numbers
+    pub relative_numbers: bool,
+    /// Show spaces, tabs and non-breaking spaces as glyphs and highlight trailing whitespace
+    pub whitespace: bool,
+    /// Draw vertical lines at each indentation level
+    pub indent_guides: bool,
+    /// Color brackets by how deeply they are nested
+    pub rainbow_brackets: bool,
+    /// How many columns one level of indentation takes
+    pub indent_width: usize,
+    /// How many columns a tab character advances to
+    pub tab_width: usize,
+    /// Indent with tab characters instead of spaces. `None` leaves it up to the language.
+    pub hard_tabs: Option<bool>,
+    /// Remove whitespace at the end of lines when saving
+    pub trim_trailing_whitespace: bool,
+    /// Whether edited files get a line break at the end when saving (`Some(true)`), lose it
+    /// (`Some(false)`) or keep what they had (`None`)
+    pub insert_final_newline: Option<bool>,
+    /// Column where a ruler is drawn to show that lines are getting too long
+    pub max_line_length: Option<usize>,
+    /// Copy files to `file~` before overwriting them
+    pub backup: bool,
+    /// Save modified files after this many seconds without input and when the terminal loses
+    /// focus
+    pub autosave: Option<usize>,
+    /// Open files read-only, like `--readonly` does. Use `view` for the current buffer.
+    pub readonly: bool,
+    /// Handle clicks and drags in the editor instead of leaving them to the terminal's own text
+    /// selection
+    pub mouse: bool,
+    /// Insert the closing bracket or quote when typing an opening one
+    pub auto_pairs: bool,
+    /// Comma-separated segments of the status line. Segments after a `|` are aligned to the right.
+    pub status_line: String,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            relative_numbers: false,
+            whitespace: false,
+            indent_guides: false,
+            rainbow_brackets: false,
+            indent_width: 4,
+            tab_width: 4,
+            hard_tabs: None,
+            trim_trailing_whitespace: false,
+            insert_final_newline: None,
+            max_line_length: None,
+            backup: false,
+            autosave: None,
+            readonly: false,
+            mouse: false,
+            auto_pairs: true,
+            status_line:
+                "modified,readonly,path|selection,language,line-ending,encoding,position,percent"
+                    .into(),
+        }
+    }
+}
+
+impl Options {
+    /// Sets option `name` to `value`. Boolean options are toggled when no value is given.
+    pub fn set(&mut self, name: &str, value: Option<&str>) -> Result<(), String> {
+        match name {
+            "relative-numbers" => set_bool(&mut self.relative_numbers, value),
+            "whitespace" => set_bool(&mut self.whitespace, value),
+            "indent-guides" => set_bool(&mut self.indent_guides, value),
+            "rainbow-brackets" => set_bool(&mut self.rainbow_brackets, value),
+            "indent-width" => set_width(&mut self.indent_width, value),
+            "tab-width" => set_width(&mut self.tab_width, value),
+            "hard-tabs" => {
+                if value == Some("auto") {
+                    self.hard_tabs = None;
+                    return Ok(());
+                }
+                let mut hard_tabs = self.hard_tabs.unwrap_or(false);
+                set_bool(&mut hard_tabs, value)?;
+                self.hard_tabs = Some(hard_tabs);
+                Ok(())
+            }
+            "trim-trailing-whitespace" => set_bool(&mut self.trim_trailing_whitespace, value),
+            "insert-final-newline" => {
+                if value == Some("auto") {
+                    self.insert_final_newline = None;
+                    return Ok(());
+                }
+                let mut insert = self.insert_final_newline.unwrap_or(false);
+                set_bool(&mut insert, value)?;
+                self.insert_final_newline = Some(insert);
+                Ok(())
+            }
+            "max-line-length" => {
+                if value == Some("off") {
+                    self.max_line_length = None;
+                    return Ok(());
+                }
+                let mut max = 0;
+                set_width(&mut max, value)?;
+                self.max_line_length = Some(max);
+                Ok(())
+            }
+            "backup" => set_bool(&mut self.backup, value),
+            "autosave" => {
+                if value == Some("off") {
+                    self.autosave = None;
+                    return Ok(());
+                }
+                let mut seconds = 0;
+                set_width(&mut seconds, value)?;
+                self.autosave = Some(seconds);
+                Ok(())
+            }
+            "readonly" => set_bool(&mut self.readonly, value),
+            "mouse" => set_bool(&mut self.mouse, value),
+            "auto-pairs" => set_bool(&mut self.auto_pairs, value),
+            "status-line" => {
+                let value = value.ok_or("expected a list of segments")?;
+                self.status_line = value.into();
+                Ok(())
+            }
+            _ => Err(format!("unknown option: {name}")),
+        }
+    }
+}
+
+fn set_bool(option: &mut bool, value: Option<&str>) -> Result<(), String> {
+    *option = match value {
+        None => !*option,
+        Some("on" | "true" | "yes") => true,
+        Some("off" | "false" | "no") => false,
+        Some(value) => return Err(format!("expected \"on\" or \"off\", got {value:?}")),
+    };
+    Ok(())
+}
+
+fn set_width(option: &mut usize, value: Option<&str>) -> Result<(), String> {
+    let value = value.ok_or("expected a number")?;
+    *option = value
+        .parse()
+        .ok()
+        .filter(|&width| width > 0)
+        .ok_or_else(|| format!("expected a positive number, got {value:?}"))?;
+    Ok(())
+}
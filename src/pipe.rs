@@ -0,0 +1,50 @@
+use std::fs::File;
+use std::io::{self, IsTerminal, Read};
+
+/// Whether the buffer should come from stdin: when it's given as `-`, or when no file is given
+/// and something is piped in
+pub fn should_read_stdin(file_path: Option<&str>) -> bool {
+    match file_path {
+        Some(file_path) => file_path == "-",
+        None => !io::stdin().is_terminal(),
+    }
+}
+
+pub fn read_stdin() -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    io::stdin().lock().read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Moves stdout out of the way so that the editor can draw on the terminal even when stdout is a
+/// pipe, and returns a handle to where it went. Keys are read from the terminal anyway once stdin
+/// isn't one.
+#[cfg(unix)]
+pub fn redirect_stdout_to_tty() -> io::Result<File> {
+    use std::os::fd::{AsRawFd, FromRawFd};
+
+    let tty = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")?;
+    unsafe {
+        let original = libc::dup(libc::STDOUT_FILENO);
+        if original < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if libc::dup2(tty.as_raw_fd(), libc::STDOUT_FILENO) < 0 {
+            let err = io::Error::last_os_error();
+            libc::close(original);
+            return Err(err);
+        }
+        Ok(File::from_raw_fd(original))
+    }
+}
+
+#[cfg(not(unix))]
+pub fn redirect_stdout_to_tty() -> io::Result<File> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "piping the output isn't supported on this platform",
+    ))
+}
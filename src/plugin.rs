@@ -28,6 +28,10 @@ impl<'a> From<&'a [u8]> for StringView<'a> {
 
 impl<'a> From<StringView<'a>> for String {
     fn from(val: StringView<'a>) -> Self {
+        // ERS_SV_EMPTY
+        if val.data.is_null() {
+            return String::new();
+        }
         unsafe {
             std::str::from_utf8(std::slice::from_raw_parts(val.data, val.len))
                 .unwrap()
@@ -48,6 +52,7 @@ impl<'a> From<&'a str> for StringView<'a> {
 
 pub type CmdCallback =
     extern "C" fn(*mut Api, *const StringView, usize, *mut libc::c_void) -> StringView;
+pub type SegmentCallback = extern "C" fn(*mut Api, *mut libc::c_void) -> StringView<'static>;
 
 #[repr(C)]
 pub struct Api {
@@ -63,6 +68,9 @@ pub struct Api {
         extern "C" fn(*mut Api, *mut libc::c_void),
         *mut libc::c_void,
     ),
+    set_sign: unsafe extern "C" fn(*mut Plugin, usize, StringView),
+    clear_signs: unsafe extern "C" fn(*mut Plugin),
+    add_segment: unsafe extern "C" fn(*mut Plugin, StringView, SegmentCallback, *mut libc::c_void),
 }
 
 impl Api {
@@ -85,6 +93,10 @@ impl Api {
         unsafe extern "C" fn update_curr_row(editor: *mut crate::Editor, new_row: StringView) {
             let new_row: &[u8] = new_row.into();
             (*editor).log(format!("Updating current row: {:?}", new_row));
+            if (*editor).refuse_edit() {
+                return;
+            }
+            (*editor).lex_stale = true;
             let row = (*editor).row();
             row.clear();
             row.extend_from_slice(
@@ -101,6 +113,24 @@ impl Api {
         ) {
             (*plugin).on_render = Some((callback, data));
         }
+        unsafe extern "C" fn set_sign(plugin: *mut Plugin, row: usize, sign: StringView) {
+            let sign: String = sign.into();
+            match sign.chars().next() {
+                Some(ch) => (*plugin).signs.insert(row, ch),
+                None => (*plugin).signs.remove(&row),
+            };
+        }
+        unsafe extern "C" fn clear_signs(plugin: *mut Plugin) {
+            (*plugin).signs.clear();
+        }
+        unsafe extern "C" fn add_segment(
+            plugin: *mut Plugin,
+            name: StringView,
+            callback: SegmentCallback,
+            data: *mut libc::c_void,
+        ) {
+            (*plugin).segments.push((name.into(), callback, data));
+        }
         Self {
             editor: editor as *mut _ as _,
             plugin,
@@ -110,6 +140,9 @@ impl Api {
             get_curr_row,
             update_curr_row,
             on_render,
+            set_sign,
+            clear_signs,
+            add_segment,
         }
     }
 }
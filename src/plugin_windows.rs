@@ -6,6 +6,10 @@ pub struct Plugin {
         extern "C" fn(*mut Api, *mut libc::c_void),
         *mut libc::c_void,
     )>,
+    /// Markers the plugin placed in the sign column, by row
+    pub signs: std::collections::HashMap<usize, char>,
+    /// Status line segments registered by the plugin, by name
+    pub segments: Vec<(String, SegmentCallback, *mut libc::c_void)>,
 }
 
 impl Plugin {
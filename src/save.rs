@@ -0,0 +1,137 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Writes `bytes` to `path` so that the file is either fully written or left as it was, even if
+/// the editor crashes or the disk fills up: the data goes to a temporary file next to it first,
+/// which then replaces the original. Symlinks are followed so that the link itself stays, and the
+/// permissions and owner of the original are kept. With `backup`, the original is copied to
+/// `path~` before it's replaced.
+pub fn write_file(path: &Path, bytes: &[u8], backup: bool) -> io::Result<()> {
+    let target = resolve_symlinks(path);
+    let metadata = fs::metadata(&target).ok();
+
+    if backup && metadata.is_some() {
+        let mut backup_path = target.clone().into_os_string();
+        backup_path.push("~");
+        fs::copy(&target, backup_path)?;
+    }
+
+    // Replacing the file would break the other links to it
+    #[cfg(unix)]
+    if let Some(metadata) = &metadata {
+        use std::os::unix::fs::MetadataExt;
+        if metadata.nlink() > 1 {
+            return write_in_place(&target, bytes);
+        }
+    }
+
+    let (temp_path, mut temp) = match create_temp_file(&target) {
+        Ok(temp) => temp,
+        // Without write access to the directory, the file can still be overwritten
+        Err(err) if err.kind() == io::ErrorKind::PermissionDenied => {
+            return write_in_place(&target, bytes);
+        }
+        Err(err) => return Err(err),
+    };
+
+    let result = fill_temp_file(&mut temp, bytes, metadata.as_ref())
+        .and_then(|()| fs::rename(&temp_path, &target));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result?;
+
+    // Make sure the rename itself survives a crash
+    #[cfg(unix)]
+    if let Some(dir) = target.parent() {
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        let _ = File::open(dir).and_then(|dir| dir.sync_all());
+    }
+    Ok(())
+}
+
+/// The file that `path` points to, or `path` itself if it isn't a symlink. Links to files that
+/// don't exist yet resolve to where the file would be created.
+fn resolve_symlinks(path: &Path) -> PathBuf {
+    let mut path = path.to_path_buf();
+    // Give up on symlink loops eventually
+    for _ in 0..40 {
+        let Ok(link) = fs::read_link(&path) else {
+            break;
+        };
+        path = match path.parent() {
+            Some(dir) => dir.join(link),
+            None => link,
+        };
+    }
+    path
+}
+
+fn create_temp_file(target: &Path) -> io::Result<(PathBuf, File)> {
+    let name = target
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let dir = target.parent().unwrap_or(Path::new(""));
+    let mut attempt = 0;
+    loop {
+        let temp_path = dir.join(format!(".{name}.{}.{attempt}.tmp", std::process::id()));
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)
+        {
+            Ok(file) => return Ok((temp_path, file)),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists && attempt < 100 => {
+                attempt += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Writes the new contents to the temporary file and gives it the original's permissions
+fn fill_temp_file(
+    temp: &mut File,
+    bytes: &[u8],
+    original: Option<&fs::Metadata>,
+) -> io::Result<()> {
+    temp.write_all(bytes)?;
+    if let Some(original) = original {
+        temp.set_permissions(original.permissions())?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            // Only root can give files away, so this fails for files owned by someone else
+            let _ = std::os::unix::fs::fchown(&*temp, Some(original.uid()), Some(original.gid()));
+        }
+    }
+    temp.sync_all()
+}
+
+fn write_in_place(target: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut file = File::create(target)?;
+    file.write_all(bytes)?;
+    file.sync_all()
+}
+
+/// Whether the current user may write to the file at `path`
+pub fn is_writable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        let Ok(path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+            return false;
+        };
+        unsafe { libc::access(path.as_ptr(), libc::W_OK) == 0 }
+    }
+    #[cfg(not(unix))]
+    {
+        fs::metadata(path).is_ok_and(|metadata| !metadata.permissions().readonly())
+    }
+}
@@ -0,0 +1,44 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// How often the swap file of a modified buffer is updated
+pub const SWAP_INTERVAL: Duration = Duration::from_secs(4);
+
+/// Where the swap file of `file_path` goes: `.name.swp` next to it, like in Vim
+pub fn swap_path(file_path: &str) -> PathBuf {
+    let path = Path::new(file_path);
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{name}.swp"))
+}
+
+/// Where a buffer that isn't a file is saved when the editor crashes
+pub fn recovery_path() -> PathBuf {
+    std::env::temp_dir().join(format!("ers-recovered-{}.txt", std::process::id()))
+}
+
+/// Identifies the contents of a buffer, to tell if the swap file is up to date
+pub fn hash(buf: &[Vec<char>]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    buf.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Swap files are always UTF-8 with `\n` line endings, whatever the file uses
+pub fn write(path: &Path, buf: &[Vec<char>]) -> io::Result<()> {
+    let text = buf
+        .iter()
+        .map(String::from_iter)
+        .collect::<Vec<_>>()
+        .join("\n");
+    crate::save::write_file(path, text.as_bytes(), false)
+}
+
+pub fn read(path: &Path) -> io::Result<Vec<Vec<char>>> {
+    let text = std::fs::read_to_string(path)?;
+    Ok(text.split('\n').map(|row| row.chars().collect()).collect())
+}
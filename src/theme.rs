@@ -0,0 +1,270 @@
+use crate::langs::Scope;
+use crossterm::style::{Attribute, Color};
+use crossterm_display::Cell;
+use std::collections::HashMap;
+
+pub const DEFAULT_THEME: &str = "default";
+
+const BUILTIN_THEMES: &[(&str, &str)] = &[
+    ("default", include_str!("../themes/default.theme")),
+    ("light", include_str!("../themes/light.theme")),
+];
+
+pub const fn rgb_color(r: u8, g: u8, b: u8) -> Color {
+    Color::Rgb { r, g, b }
+}
+
+/// How many colors the terminal can show
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColorDepth {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+}
+
+impl ColorDepth {
+    fn detect() -> Self {
+        let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+        let term = std::env::var("TERM").unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            ColorDepth::TrueColor
+        } else if term.contains("256color") {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+}
+
+/// The 16 basic colors with the RGB values xterm uses for them
+const ANSI_16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::DarkRed, (205, 0, 0)),
+    (Color::DarkGreen, (0, 205, 0)),
+    (Color::DarkYellow, (205, 205, 0)),
+    (Color::DarkBlue, (0, 0, 238)),
+    (Color::DarkMagenta, (205, 0, 205)),
+    (Color::DarkCyan, (0, 205, 205)),
+    (Color::Grey, (229, 229, 229)),
+    (Color::DarkGrey, (127, 127, 127)),
+    (Color::Red, (255, 0, 0)),
+    (Color::Green, (0, 255, 0)),
+    (Color::Yellow, (255, 255, 0)),
+    (Color::Blue, (92, 92, 255)),
+    (Color::Magenta, (255, 0, 255)),
+    (Color::Cyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+/// Levels of each channel in the 6x6x6 color cube of the 256 color palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    d(r1, r2) + d(g1, g2) + d(b1, b2)
+}
+
+fn ansi_to_rgb(n: u8) -> (u8, u8, u8) {
+    match n {
+        0..=15 => ANSI_16[n as usize].1,
+        16..=231 => {
+            let n = n - 16;
+            (
+                CUBE_LEVELS[(n / 36) as usize],
+                CUBE_LEVELS[(n / 6 % 6) as usize],
+                CUBE_LEVELS[(n % 6) as usize],
+            )
+        }
+        232..=255 => {
+            let level = 8 + (n - 232) * 10;
+            (level, level, level)
+        }
+    }
+}
+
+fn rgb_to_ansi256(rgb: (u8, u8, u8)) -> u8 {
+    let nearest_level = |channel: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|&i| CUBE_LEVELS[i].abs_diff(channel))
+            .unwrap() as u8
+    };
+    let cube = 16 + 36 * nearest_level(rgb.0) + 6 * nearest_level(rgb.1) + nearest_level(rgb.2);
+
+    let average = ((rgb.0 as u32 + rgb.1 as u32 + rgb.2 as u32) / 3) as u8;
+    let gray = 232 + (average.saturating_sub(3) / 10).min(23);
+
+    if distance(ansi_to_rgb(gray), rgb) < distance(ansi_to_rgb(cube), rgb) {
+        gray
+    } else {
+        cube
+    }
+}
+
+fn rgb_to_ansi16(rgb: (u8, u8, u8)) -> Color {
+    ANSI_16
+        .iter()
+        .min_by_key(|(_, ansi)| distance(*ansi, rgb))
+        .unwrap()
+        .0
+}
+
+/// Converts `color` to the closest one the terminal can show
+fn degrade(color: Color, depth: ColorDepth) -> Color {
+    match (color, depth) {
+        (Color::Rgb { r, g, b }, ColorDepth::Ansi256) => {
+            Color::AnsiValue(rgb_to_ansi256((r, g, b)))
+        }
+        (Color::Rgb { r, g, b }, ColorDepth::Ansi16) => rgb_to_ansi16((r, g, b)),
+        (Color::AnsiValue(n), ColorDepth::Ansi16) => rgb_to_ansi16(ansi_to_rgb(n)),
+        (color, _) => color,
+    }
+}
+
+fn parse_color(s: &str) -> Option<Color> {
+    if let Some(hex) = s.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        return Some(rgb_color(channel(0)?, channel(2)?, channel(4)?));
+    }
+    if let Ok(n) = s.parse::<u8>() {
+        return Some(Color::AnsiValue(n));
+    }
+    match s {
+        "reset" | "default" => Some(Color::Reset),
+        _ => Color::try_from(s).ok(),
+    }
+}
+
+fn parse_attribute(s: &str) -> Option<Attribute> {
+    Some(match s {
+        "normal" => Attribute::Reset,
+        "bold" => Attribute::Bold,
+        "dim" => Attribute::Dim,
+        "italic" => Attribute::Italic,
+        "underlined" => Attribute::Underlined,
+        "reverse" => Attribute::Reverse,
+        "crossed_out" => Attribute::CrossedOut,
+        _ => return None,
+    })
+}
+
+/// A style from a theme file. Missing parts are inherited from the parent scope.
+#[derive(Debug, Clone, Copy, Default)]
+struct Style {
+    fg: Option<Color>,
+    bg: Option<Color>,
+    attr: Option<Attribute>,
+}
+
+/// Maps highlight scopes (`keyword`, `diff.added`...) and UI elements (`ui.gutter`,
+/// `ui.status`...) to colors.
+///
+/// Theme files have one `name = [fg] [on bg] [attribute]` entry per line, where colors are either
+/// names (`dark_green`), `#rrggbb` or numbers from the 256 color palette:
+///
+/// ```text
+/// # Comments start with a hash
+/// text = white on #181818
+/// keyword = yellow bold
+/// ui.gutter.current = white bold
+/// ```
+pub struct Theme {
+    styles: HashMap<String, Style>,
+}
+
+impl Theme {
+    /// Loads a theme from the user's theme directory (`~/.config/ers/themes/<name>.theme`), a
+    /// path, or one of the built-in themes
+    pub fn load(name: &str) -> Result<Self, String> {
+        let path = if name.contains('/') || name.ends_with(".theme") {
+            Some(std::path::PathBuf::from(name))
+        } else {
+            themes_dir().map(|dir| dir.join(format!("{name}.theme")))
+        };
+
+        if let Some(path) = path.filter(|path| path.exists()) {
+            let src = std::fs::read_to_string(&path)
+                .map_err(|err| format!("could not read {}: {err}", path.display()))?;
+            return Self::parse(name, &src);
+        }
+
+        let (_, src) = BUILTIN_THEMES
+            .iter()
+            .find(|(builtin, _)| *builtin == name)
+            .ok_or_else(|| format!("unknown theme: {name}"))?;
+        Self::parse(name, src)
+    }
+
+    pub fn parse(name: &str, src: &str) -> Result<Self, String> {
+        let depth = ColorDepth::detect();
+        let mut styles = HashMap::new();
+
+        for (line_idx, line) in src.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let err = |msg: String| format!("{name}:{}: {msg}", line_idx + 1);
+
+            let (key, spec) = line
+                .split_once('=')
+                .ok_or_else(|| err("expected `name = style`".into()))?;
+            let mut style = Style::default();
+            let mut tokens = spec.split_whitespace();
+            while let Some(token) = tokens.next() {
+                if token == "on" {
+                    let bg = tokens
+                        .next()
+                        .ok_or_else(|| err("expected a color after `on`".into()))?;
+                    let bg = parse_color(bg).ok_or_else(|| err(format!("unknown color: {bg}")))?;
+                    style.bg = Some(degrade(bg, depth));
+                } else if let Some(attr) = parse_attribute(token) {
+                    style.attr = Some(attr);
+                } else if let Some(fg) = parse_color(token) {
+                    style.fg = Some(degrade(fg, depth));
+                } else {
+                    return Err(err(format!("unknown color or attribute: {token}")));
+                }
+            }
+            styles.insert(key.trim().to_string(), style);
+        }
+
+        Ok(Self { styles })
+    }
+
+    /// Builds a cell styled like `key`. Anything `key` doesn't set comes from its parents
+    /// (`ui.gutter.current` -> `ui.gutter` -> `ui`) and finally from `text`.
+    pub fn cell(&self, key: &str, ch: char) -> Cell {
+        let mut style = Style::default();
+        let parents = std::iter::successors(Some(key), |key| key.rsplit_once('.').map(|(p, _)| p))
+            .chain(std::iter::once("text"));
+        for parent in parents {
+            if let Some(parent) = self.styles.get(parent) {
+                style.fg = style.fg.or(parent.fg);
+                style.bg = style.bg.or(parent.bg);
+                style.attr = style.attr.or(parent.attr);
+            }
+        }
+        Cell {
+            ch,
+            fg: style.fg.unwrap_or(Color::Reset),
+            bg: style.bg.unwrap_or(Color::Reset),
+            attr: style.attr.unwrap_or(Attribute::Reset),
+        }
+    }
+
+    pub fn scope_cell(&self, scope: Scope, ch: char) -> Cell {
+        self.cell(scope.name(), ch)
+    }
+}
+
+fn themes_dir() -> Option<std::path::PathBuf> {
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .map(std::path::PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| std::path::Path::new(&home).join(".config"))
+        })?;
+    Some(config.join("ers").join("themes"))
+}